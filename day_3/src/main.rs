@@ -1,5 +1,9 @@
-use std::io::{stdin, Read};
+use std::cmp::{max, min, Ordering};
+use std::fmt::{Display, Formatter};
+use std::iter::Sum;
+use std::ops::Add;
 use itertools::Itertools;
+use shared::{parse_option_value, print_program_header, read_input, ArgumentError};
 
 type LobbyResult<'a, T> = Result<T, LobbyError<'a>>;
 type LobbyMultiResult<'a, T> = Result<Vec<T>, Vec<LobbyError<'a>>>;
@@ -7,15 +11,170 @@ type LobbyMultiResult<'a, T> = Result<Vec<T>, Vec<LobbyError<'a>>>;
 #[derive(Debug, PartialEq)]
 enum LobbyError<'a> {
     InvalidInput(&'a str),
+    InvalidBatteryCount { bank: &'a [u64], requested: usize },
+    UnsatisfiableSelection { bank: &'a [u64], requested: usize },
 }
 
 const DAY: u8 = 3;
 
+const HIGHLIGHT_START: &str = "\x1b[1;32m";
+const HIGHLIGHT_END: &str = "\x1b[0m";
+
+/// Batteries chosen from a single bank, as positions within the bank and the ratings found there.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct BatterySelection {
+    indices: Vec<usize>,
+    batteries: Vec<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Arguments {
+    input_path: Option<String>,
+    counts: Vec<usize>,
+    options: SelectionOptions,
+    delimiter: Option<char>,
+    per_bank: bool,
+    show_selection: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SelectionMode {
+    Largest,
+    Smallest,
+    /// Largest voltage when ratings have several digits and are concatenated. Gap limits don't apply here.
+    LargestConcatenation,
+}
+
+/// Rules the selection engine follows when picking batteries from a bank.
+#[derive(Debug, Clone, PartialEq)]
+struct SelectionOptions {
+    mode: SelectionMode,
+    /// Whether the first selected battery may be a zero.
+    allow_leading_zero: bool,
+    /// Minimum number of batteries skipped between two consecutive selected batteries.
+    min_gap: usize,
+    /// Maximum number of batteries skipped between two consecutive selected batteries.
+    max_gap: Option<usize>,
+}
+
+impl Default for SelectionOptions {
+    fn default() -> Self {
+        SelectionOptions {
+            mode: SelectionMode::Largest,
+            allow_leading_zero: true,
+            min_gap: 0,
+            max_gap: None,
+        }
+    }
+}
+
+impl SelectionOptions {
+    #[cfg(test)]
+    fn smallest(allow_leading_zero: bool) -> Self {
+        SelectionOptions {
+            mode: SelectionMode::Smallest,
+            allow_leading_zero,
+            ..SelectionOptions::default()
+        }
+    }
+
+    /// Returns the better of two battery ratings according to the selection mode.
+    fn prefer(&self, a: u64, b: u64) -> u64 {
+        match self.mode {
+            SelectionMode::Largest | SelectionMode::LargestConcatenation => a.max(b),
+            SelectionMode::Smallest => a.min(b),
+        }
+    }
+}
+
+/// Voltage of arbitrary size, stored as decimal digits with the most significant digit first and without
+/// leading zeroes (zero itself is an empty digit list).
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Voltage(Vec<u8>);
+
+impl Voltage {
+    fn from_digits(digits: &[u8]) -> Self {
+        let significant_digits = digits.iter().skip_while(|digit| **digit == 0).copied().collect_vec();
+
+        Voltage(significant_digits)
+    }
+
+    /// Builds the voltage by concatenating the decimal representations of the ratings.
+    fn from_ratings(ratings: &[u64]) -> Self {
+        let digits = ratings
+            .iter()
+            .flat_map(|rating| rating.to_string().into_bytes())
+            .map(|digit| digit - b'0')
+            .collect_vec();
+
+        Voltage::from_digits(&digits)
+    }
+}
+
+impl Add for Voltage {
+    type Output = Voltage;
+
+    fn add(self, other: Voltage) -> Voltage {
+        let mut sum = Vec::with_capacity(self.0.len().max(other.0.len()) + 1);
+        let mut carry = 0;
+
+        let mut lhs = self.0.iter().rev();
+        let mut rhs = other.0.iter().rev();
+
+        loop {
+            let (a, b) = (lhs.next(), rhs.next());
+            if a.is_none() && b.is_none() && carry == 0 {
+                break;
+            }
+
+            let digit_sum = a.unwrap_or(&0) + b.unwrap_or(&0) + carry;
+            sum.push(digit_sum % 10);
+            carry = digit_sum / 10;
+        }
+
+        sum.reverse();
+        Voltage(sum)
+    }
+}
+
+impl Sum for Voltage {
+    fn sum<I: Iterator<Item = Voltage>>(iter: I) -> Voltage {
+        iter.fold(Voltage::default(), |accum, voltage| accum + voltage)
+    }
+}
+
+impl Display for Voltage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "0");
+        }
+
+        self.0.iter().try_for_each(|digit| write!(f, "{digit}"))
+    }
+}
+
 fn main() {
-    let mut data = String::new();
-    stdin().read_to_string(&mut data).unwrap();
+    print_program_header(DAY, "Lobby");
+
+    let arguments = match parse_arguments(std::env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(error) => {
+            eprintln!("Invalid arguments: {error:?}");
+            eprintln!(
+                "Usage: day_3 [INPUT|-] [--count N]... [--smallest] [--no-leading-zero] [--min-gap N] [--max-gap N] \
+                 [--delimiter C] [--per-bank] [--show-selection]"
+            );
+            return;
+        }
+    };
+
+    let data = read_input(DAY, arguments.input_path.as_deref());
+    let parsed_banks = match arguments.delimiter {
+        Some(delimiter) => parse_delimited_battery_banks(&data, delimiter),
+        None => parse_battery_banks(&data),
+    };
 
-    let battery_banks = match parse_battery_banks(&data) {
+    let battery_banks = match parsed_banks {
         Ok(v) => v,
         Err(errors) => {
             eprintln!("Cannot read battery banks:");
@@ -24,56 +183,341 @@ fn main() {
         }
     };
 
-    println!("Max possible voltage: {}", find_total_largest_voltage(battery_banks.clone(), 2));
-    println!("Max possible voltage (overloaded to 12 batteries): {}", find_total_largest_voltage(battery_banks.clone(), 12));
+    let label = match arguments.options.mode {
+        SelectionMode::Largest | SelectionMode::LargestConcatenation => "Max possible voltage",
+        SelectionMode::Smallest => "Min possible voltage",
+    };
+
+    for count in &arguments.counts {
+        match find_total_voltage(&battery_banks, *count, &arguments.options) {
+            Ok(voltage) => println!("{label} ({count} batteries per bank): {voltage}"),
+            Err(errors) => {
+                eprintln!("Cannot select {count} batteries: {errors:?}");
+                continue;
+            }
+        }
+
+        if arguments.per_bank || arguments.show_selection {
+            print_bank_report(&battery_banks, *count, &arguments);
+            println!();
+        }
+    }
 }
 
-fn find_total_largest_voltage(battery_banks: Vec<Vec<u8>>, count: usize) -> u128 {
-    battery_banks
-        .into_iter()
-        .map(|bank| find_largest_possible_voltage(bank, count))
-        .sum()
+fn print_bank_report(battery_banks: &[Vec<u64>], count: usize, arguments: &Arguments) {
+    for (index, bank) in battery_banks.iter().enumerate() {
+        let Ok(selection) = find_combination(bank, count, &arguments.options) else {
+            continue;
+        };
+        let voltage = Voltage::from_ratings(&selection.batteries);
+
+        if arguments.show_selection {
+            let separator = arguments.delimiter.map(String::from).unwrap_or_default();
+            let rendered_bank = render_bank_selection(bank, &selection, &separator);
+
+            println!("{:>5}: {rendered_bank}  {voltage}", index + 1);
+        } else {
+            println!("{:>5}: {voltage}", index + 1);
+        }
+    }
 }
 
-fn find_largest_possible_voltage(battery_bank: Vec<u8>, count: usize) -> u128 {
-    let batteries = find_largest_possible_combination(battery_bank, count);
+fn parse_arguments(mut args: impl Iterator<Item = String>) -> Result<Arguments, ArgumentError> {
+    let mut arguments = Arguments {
+        input_path: None,
+        counts: Vec::new(),
+        options: SelectionOptions::default(),
+        delimiter: None,
+        per_bank: false,
+        show_selection: false,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--count" => arguments.counts.push(parse_option_value(&arg, &mut args)?),
+            "--smallest" => arguments.options.mode = SelectionMode::Smallest,
+            "--no-leading-zero" => arguments.options.allow_leading_zero = false,
+            "--min-gap" => arguments.options.min_gap = parse_option_value(&arg, &mut args)?,
+            "--max-gap" => arguments.options.max_gap = Some(parse_option_value(&arg, &mut args)?),
+            "--delimiter" => arguments.delimiter = Some(parse_option_value(&arg, &mut args)?),
+            "--per-bank" => arguments.per_bank = true,
+            "--show-selection" => arguments.show_selection = true,
+            option if option.starts_with("--") => return Err(ArgumentError::UnknownOption(arg)),
+            _ if arguments.input_path.is_none() => arguments.input_path = Some(arg),
+            _ => return Err(ArgumentError::UnexpectedArgument(arg)),
+        }
+    }
+
+    if arguments.counts.is_empty() {
+        arguments.counts = vec![2, 12];
+    }
+
+    if arguments.delimiter.is_some() {
+        let digit_wise_options = [
+            ("--smallest", arguments.options.mode == SelectionMode::Smallest),
+            ("--min-gap", arguments.options.min_gap != 0),
+            ("--max-gap", arguments.options.max_gap.is_some()),
+        ];
+
+        if let Some((option, _)) = digit_wise_options.into_iter().find(|(_, used)| *used) {
+            return Err(ArgumentError::ConflictingOptions("--delimiter".to_string(), option.to_string()));
+        }
+
+        arguments.options.mode = SelectionMode::LargestConcatenation;
+    }
 
-    let voltage = batteries
+    Ok(arguments)
+}
+
+fn find_total_voltage<'a>(
+    battery_banks: &'a [Vec<u64>],
+    count: usize,
+    options: &SelectionOptions,
+) -> Result<Voltage, Vec<LobbyError<'a>>> {
+    let (voltages, errors): (Vec<_>, Vec<_>) = battery_banks
         .iter()
-        .fold((0, count as i32 - 1), |(accum, exponent), battery| {
-            let value = *battery as u128 * 10u128.pow(exponent as u32);
+        .map(|bank| find_voltage(bank, count, options))
+        .partition_result();
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
 
-            (accum + value, exponent - 1)
-        });
+    Ok(voltages.into_iter().sum())
+}
+
+fn find_voltage<'a>(battery_bank: &'a [u64], count: usize, options: &SelectionOptions) -> LobbyResult<'a, Voltage> {
+    let selection = find_combination(battery_bank, count, options)?;
 
-    voltage.0
+    Ok(Voltage::from_ratings(&selection.batteries))
 }
 
-fn find_largest_possible_combination(battery_bank: Vec<u8>, count: usize) -> Vec<u8> {
-    let mut current_start_offset = 0;
-    let mut combination = Vec::new();
+/// Picks `count` batteries from the bank (keeping their order) so that the resulting voltage is as large as
+/// possible. When the same digit is available at several positions, the leftmost one is chosen.
+#[cfg(test)]
+fn find_largest_possible_combination(battery_bank: &[u64], count: usize) -> LobbyResult<'_, BatterySelection> {
+    find_combination(battery_bank, count, &SelectionOptions::default())
+}
+
+/// Picks `count` batteries from the bank (keeping their order) so that the resulting voltage is the best one
+/// allowed by `options`. Among equally good selections, the one using the leftmost batteries is returned.
+///
+/// The search walks the selection one battery at a time, keeping every position that can hold the best digit
+/// found so far for that step. Each step only looks at positions reachable (within the gap limits) from the
+/// previous step's positions, so the whole search is O(count × bank length).
+fn find_combination<'a>(
+    battery_bank: &'a [u64],
+    count: usize,
+    options: &SelectionOptions,
+) -> LobbyResult<'a, BatterySelection> {
+    if count > battery_bank.len() {
+        return Err(LobbyError::InvalidBatteryCount { bank: battery_bank, requested: count });
+    }
+
+    if count == 0 {
+        return Ok(BatterySelection::default());
+    }
+
+    if options.mode == SelectionMode::LargestConcatenation {
+        return find_largest_concatenation(battery_bank, count, options.allow_leading_zero);
+    }
+
+    let unsatisfiable = LobbyError::UnsatisfiableSelection { bank: battery_bank, requested: count };
+
+    let step = options.min_gap + 1;
+    let max_step = options.max_gap.map_or(usize::MAX, |max_gap| max_gap.saturating_add(1));
+    if step > max_step && count > 1 {
+        return Err(unsatisfiable);
+    }
+
+    // The last position the n-th selected battery may occupy, leaving room for the remaining ones.
+    let Some(reserved_end_offset) = (count - 1).checked_mul(step) else {
+        return Err(unsatisfiable);
+    };
+    let Some(first_latest_index) = (battery_bank.len() - 1).checked_sub(reserved_end_offset) else {
+        return Err(unsatisfiable);
+    };
+    let latest_index = |current_battery: usize| first_latest_index + current_battery * step;
+
+    let mut frontiers: Vec<Vec<usize>> = Vec::with_capacity(count);
 
     for current_battery in 0..count {
-        let reserved_end_offset = count - current_battery - 1;
-        let max_selectable_index = battery_bank.len() - reserved_end_offset;
+        let candidates = match frontiers.last() {
+            None => (0..=latest_index(0))
+                .filter(|index| options.allow_leading_zero || battery_bank[*index] != 0)
+                .collect_vec(),
+            Some(previous) => reachable_positions(previous, step, max_step, latest_index(current_battery)),
+        };
+
+        let Some(best_value) = candidates.iter().map(|index| battery_bank[*index]).reduce(|a, b| options.prefer(a, b))
+        else {
+            return Err(unsatisfiable);
+        };
+
+        frontiers.push(candidates.into_iter().filter(|index| battery_bank[*index] == best_value).collect_vec());
+    }
+
+    // Drop positions that cannot be continued into a complete best selection, then take the leftmost path.
+    for current_battery in (0..count - 1).rev() {
+        let next = &frontiers[current_battery + 1];
+        let continuable = frontiers[current_battery]
+            .iter()
+            .copied()
+            .filter(|index| first_reachable(next, *index, step, max_step).is_some())
+            .collect_vec();
+
+        frontiers[current_battery] = continuable;
+    }
 
-        let selectable_range = &battery_bank[current_start_offset..max_selectable_index];
-        let max_selectable_value = selectable_range.iter().max().unwrap();
+    let mut selection = BatterySelection::default();
+    let mut current_index = frontiers[0][0];
 
-        let first_index = &selectable_range.iter().position(|x| x == max_selectable_value).unwrap() + current_start_offset;
+    for (current_battery, frontier) in frontiers.iter().enumerate() {
+        if current_battery > 0 {
+            current_index = first_reachable(frontier, current_index, step, max_step).unwrap();
+        }
+
+        selection.indices.push(current_index);
+        selection.batteries.push(battery_bank[current_index]);
+    }
+
+    Ok(selection)
+}
+
+/// Picks `count` multi-digit ratings (keeping their order) whose concatenation is the largest decimal number.
+///
+/// Unlike single digits, a larger rating isn't necessarily a better pick (`9` then `30` beats `30` then `7`), so this
+/// compares whole concatenations: `best[r]` holds the largest concatenation of `r` ratings from the part of the bank
+/// already processed (right to left), and each rating is either prepended to `best[r - 1]` or skipped.
+fn find_largest_concatenation(
+    battery_bank: &[u64],
+    count: usize,
+    allow_leading_zero: bool,
+) -> LobbyResult<'_, BatterySelection> {
+    let ratings = battery_bank.iter().map(|rating| rating.to_string()).collect_vec();
+    let bank_len = battery_bank.len();
+
+    let mut best: Vec<Option<String>> = vec![None; count + 1];
+    best[0] = Some(String::new());
+
+    // taken[index][r]: whether the best concatenation of r ratings starting at index uses the rating at index.
+    let mut taken = vec![vec![false; count + 1]; bank_len];
+
+    for index in (0..bank_len).rev() {
+        for remaining in (1..=count.min(bank_len - index)).rev() {
+            let is_first = remaining == count;
+            if is_first && !allow_leading_zero && battery_bank[index] == 0 {
+                continue;
+            }
+
+            let Some(rest) = &best[remaining - 1] else {
+                continue;
+            };
+
+            let candidate = format!("{}{rest}", ratings[index]);
+            let is_better = match &best[remaining] {
+                None => true,
+                Some(current) => compare_decimal(&candidate, current) != Ordering::Less,
+            };
+
+            if is_better {
+                best[remaining] = Some(candidate);
+                taken[index][remaining] = true;
+            }
+        }
+    }
+
+    if best[count].is_none() {
+        return Err(LobbyError::UnsatisfiableSelection { bank: battery_bank, requested: count });
+    }
+
+    let mut selection = BatterySelection::default();
+    let mut remaining = count;
 
-        current_start_offset = first_index + 1;
-        combination.push(*max_selectable_value);
+    for (index, taken_at_index) in taken.iter().enumerate() {
+        if remaining == 0 {
+            break;
+        }
+
+        if taken_at_index[remaining] {
+            selection.indices.push(index);
+            selection.batteries.push(battery_bank[index]);
+            remaining -= 1;
+        }
     }
 
-    combination
+    Ok(selection)
 }
 
-fn parse_battery_banks(data: &str) -> LobbyMultiResult<'_, Vec<u8>> {
+/// Compares two strings of decimal digits by their numeric value.
+fn compare_decimal(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// Collects (in order) all positions up to `latest_index` that can directly follow any of the sorted `previous`
+/// positions.
+fn reachable_positions(previous: &[usize], step: usize, max_step: usize, latest_index: usize) -> Vec<usize> {
+    let mut positions = Vec::new();
+    let mut next_unvisited = 0;
+
+    for index in previous {
+        let start = max(index + step, next_unvisited);
+        let end = min(index.saturating_add(max_step), latest_index);
+
+        if start <= end {
+            positions.extend(start..=end);
+            next_unvisited = end + 1;
+        }
+    }
+
+    positions
+}
+
+/// Finds the leftmost of the sorted `positions` that can directly follow `index`.
+fn first_reachable(positions: &[usize], index: usize, step: usize, max_step: usize) -> Option<usize> {
+    let start = positions.partition_point(|position| *position < index + step);
+
+    positions
+        .get(start)
+        .copied()
+        .filter(|position| *position <= index.saturating_add(max_step))
+}
+
+/// Renders the bank as a line of ratings with the selected batteries highlighted.
+fn render_bank_selection(battery_bank: &[u64], selection: &BatterySelection, separator: &str) -> String {
+    battery_bank
+        .iter()
+        .enumerate()
+        .map(|(index, battery)| {
+            if selection.indices.contains(&index) {
+                format!("{HIGHLIGHT_START}{battery}{HIGHLIGHT_END}")
+            } else {
+                battery.to_string()
+            }
+        })
+        .join(separator)
+}
+
+fn parse_battery_banks(data: &str) -> LobbyMultiResult<'_, Vec<u64>> {
+    parse_lines(data, parse_line)
+}
+
+/// Parses banks written as delimited multi-digit ratings, e.g. `12,7,30,4`.
+fn parse_delimited_battery_banks(data: &str, delimiter: char) -> LobbyMultiResult<'_, Vec<u64>> {
+    parse_lines(data, |line| parse_delimited_line(line, delimiter))
+}
+
+fn parse_lines<'a>(
+    data: &'a str,
+    parse: impl Fn(&'a str) -> LobbyResult<'a, Vec<u64>>,
+) -> LobbyMultiResult<'a, Vec<u64>> {
     let (banks, errors): (Vec<_>, Vec<_>) = data
         .lines()
         .filter(|line| !line.trim().is_empty())
-        .map(|line| parse_line(line))
+        .map(parse)
         .partition_result();
 
     if !errors.is_empty() {
@@ -83,11 +527,10 @@ fn parse_battery_banks(data: &str) -> LobbyMultiResult<'_, Vec<u8>> {
     Ok(banks)
 }
 
-fn parse_line(line: &str) -> LobbyResult<'_, Vec<u8>> {
+fn parse_line(line: &str) -> LobbyResult<'_, Vec<u64>> {
     let parsed = line
         .chars()
-        .map(|char| char.to_digit(10).map(|digit| digit as u8))
-        .flatten()
+        .filter_map(|char| char.to_digit(10).map(u64::from))
         .collect_vec();
 
     if parsed.len() != line.len() {
@@ -97,10 +540,17 @@ fn parse_line(line: &str) -> LobbyResult<'_, Vec<u8>> {
     Ok(parsed)
 }
 
+fn parse_delimited_line(line: &str, delimiter: char) -> LobbyResult<'_, Vec<u64>> {
+    line.split(delimiter)
+        .map(|rating| rating.trim().parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| LobbyError::InvalidInput(line))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::LobbyError::InvalidInput;
+    use crate::LobbyError::{InvalidBatteryCount, InvalidInput, UnsatisfiableSelection};
 
     #[test]
     fn test_parse_battery_banks_valid() {
@@ -123,35 +573,244 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_delimited_battery_banks() {
+        assert_eq!(
+            parse_delimited_battery_banks("12,7,30,4\n\n5, 60 ,7", ','),
+            Ok(vec![vec![12, 7, 30, 4], vec![5, 60, 7]])
+        );
+        assert_eq!(
+            parse_delimited_battery_banks("12,7\n1,,2\n3;4", ','),
+            Err(vec![InvalidInput("1,,2"), InvalidInput("3;4")])
+        );
+    }
+
+    #[test]
+    fn test_find_largest_concatenation() {
+        let options = SelectionOptions {
+            mode: SelectionMode::LargestConcatenation,
+            ..SelectionOptions::default()
+        };
+
+        // 30 is the largest rating, but 9 followed by 30 beats 30 followed by 7.
+        assert_eq!(
+            find_combination(&[9, 30, 4, 7], 2, &options),
+            Ok(BatterySelection {
+                indices: vec![0, 1],
+                batteries: vec![9, 30]
+            })
+        );
+
+        // Longer concatenations always win, even when they start with a smaller digit.
+        assert_eq!(
+            find_voltage(&[9, 12, 345, 8], 2, &options).map(|voltage| voltage.to_string()),
+            Ok("12345".to_string())
+        );
+    }
+
+    #[test]
+    fn test_find_largest_concatenation_matches_digit_wise() {
+        let options = SelectionOptions {
+            mode: SelectionMode::LargestConcatenation,
+            ..SelectionOptions::default()
+        };
+        let battery_bank = [8, 1, 8, 1, 8, 1, 9, 1, 1, 1, 1, 2, 1, 1, 1];
+
+        assert_eq!(
+            find_combination(&battery_bank, 12, &options),
+            find_combination(&battery_bank, 12, &SelectionOptions::default())
+        );
+    }
+
     #[test]
     fn test_find_largest_combination() {
         assert_eq!(
-            find_largest_possible_combination(vec![9, 8, 7, 1, 1, 7, 8, 9], 3),
-            vec![9, 8, 9]
+            find_largest_possible_combination(&[9, 8, 7, 1, 1, 7, 8, 9], 3),
+            Ok(BatterySelection {
+                indices: vec![0, 1, 7],
+                batteries: vec![9, 8, 9]
+            })
+        );
+
+        assert_eq!(
+            find_largest_possible_combination(&[9, 8, 7, 1, 1, 7, 8, 7], 3),
+            Ok(BatterySelection {
+                indices: vec![0, 1, 6],
+                batteries: vec![9, 8, 8]
+            })
+        );
+    }
+
+    #[test]
+    fn test_find_largest_combination_prefers_leftmost() {
+        assert_eq!(
+            find_largest_possible_combination(&[1, 9, 9, 1, 9], 2).map(|selection| selection.indices),
+            Ok(vec![1, 2])
+        );
+    }
+
+    #[test]
+    fn test_find_smallest_combination() {
+        assert_eq!(
+            find_combination(&[3, 0, 2, 0, 5, 1], 3, &SelectionOptions::smallest(true)),
+            Ok(BatterySelection {
+                indices: vec![1, 3, 5],
+                batteries: vec![0, 0, 1]
+            })
         );
 
         assert_eq!(
-            find_largest_possible_combination(vec![9, 8, 7, 1, 1, 7, 8, 7], 3),
-            vec![9, 8, 8]
+            find_combination(&[3, 0, 2, 0, 5, 1], 3, &SelectionOptions::smallest(false)),
+            Ok(BatterySelection {
+                indices: vec![2, 3, 5],
+                batteries: vec![2, 0, 1]
+            })
+        );
+    }
+
+    #[test]
+    fn test_find_combination_min_gap() {
+        let options = SelectionOptions {
+            min_gap: 1,
+            ..SelectionOptions::default()
+        };
+
+        assert_eq!(
+            find_combination(&[9, 9, 1, 8, 7], 3, &options),
+            Ok(BatterySelection {
+                indices: vec![0, 2, 4],
+                batteries: vec![9, 1, 7]
+            })
+        );
+    }
+
+    #[test]
+    fn test_find_combination_max_gap_looks_past_leftmost() {
+        let options = SelectionOptions {
+            max_gap: Some(0),
+            ..SelectionOptions::default()
+        };
+
+        // The leftmost 9 would force the 1 right after it, the second 9 allows the 8.
+        assert_eq!(
+            find_combination(&[9, 1, 9, 8, 2], 2, &options),
+            Ok(BatterySelection {
+                indices: vec![2, 3],
+                batteries: vec![9, 8]
+            })
+        );
+    }
+
+    #[test]
+    fn test_find_combination_unsatisfiable() {
+        let options = SelectionOptions {
+            min_gap: 2,
+            ..SelectionOptions::default()
+        };
+
+        assert_eq!(
+            find_combination(&[1, 2, 3, 4], 3, &options),
+            Err(UnsatisfiableSelection { bank: &[1, 2, 3, 4], requested: 3 })
+        );
+        assert_eq!(
+            find_combination(&[0, 0], 1, &SelectionOptions::smallest(false)),
+            Err(UnsatisfiableSelection { bank: &[0, 0], requested: 1 })
+        );
+    }
+
+    #[test]
+    fn test_render_bank_selection() {
+        let selection = find_largest_possible_combination(&[1, 9, 2, 8], 2).unwrap();
+
+        assert_eq!(
+            render_bank_selection(&[1, 9, 2, 8], &selection, ""),
+            format!("1{HIGHLIGHT_START}9{HIGHLIGHT_END}2{HIGHLIGHT_START}8{HIGHLIGHT_END}")
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        let args = ["input.txt", "--count", "3", "--smallest", "--max-gap", "1", "--per-bank"];
+
+        assert_eq!(
+            parse_arguments(args.into_iter().map(String::from)),
+            Ok(Arguments {
+                input_path: Some("input.txt".to_string()),
+                counts: vec![3],
+                options: SelectionOptions {
+                    max_gap: Some(1),
+                    ..SelectionOptions::smallest(true)
+                },
+                delimiter: None,
+                per_bank: true,
+                show_selection: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_invalid() {
+        let parse = |args: &[&str]| parse_arguments(args.iter().map(|arg| arg.to_string()));
+
+        assert_eq!(parse(&[]).map(|arguments| arguments.counts), Ok(vec![2, 12]));
+        assert_eq!(parse(&["--count"]), Err(ArgumentError::MissingValue("--count".to_string())));
+        assert_eq!(
+            parse(&["--count", "x"]),
+            Err(ArgumentError::InvalidValue { option: "--count".to_string(), value: "x".to_string() })
+        );
+        assert_eq!(parse(&["--bogus"]), Err(ArgumentError::UnknownOption("--bogus".to_string())));
+        assert_eq!(parse(&["a", "b"]), Err(ArgumentError::UnexpectedArgument("b".to_string())));
+        assert_eq!(
+            parse(&["--delimiter", ",", "--min-gap", "1"]),
+            Err(ArgumentError::ConflictingOptions("--delimiter".to_string(), "--min-gap".to_string()))
         );
     }
 
     #[test]
     fn test_find_voltage() {
         assert_eq!(
-            find_largest_possible_voltage(vec![4, 5, 6, 1, 1, 1, 8, 9], 3),
-            689
+            find_voltage(&[4, 5, 6, 1, 1, 1, 8, 9], 3, &SelectionOptions::default()).map(|voltage| voltage.to_string()),
+            Ok("689".to_string())
+        );
+    }
+
+    #[test]
+    fn test_find_voltage_beyond_u128() {
+        let battery_bank = (0..60).map(|index| index % 10).collect_vec();
+        let voltage = find_voltage(&battery_bank, 45, &SelectionOptions::default()).unwrap();
+
+        assert_eq!(
+            voltage.to_string(),
+            "967890123456789012345678901234567890123456789"
         );
     }
 
+    #[test]
+    fn test_find_voltage_invalid_count() {
+        assert_eq!(
+            find_voltage(&[1, 2, 3], 4, &SelectionOptions::default()),
+            Err(InvalidBatteryCount { bank: &[1, 2, 3], requested: 4 })
+        );
+    }
 
+    #[test]
+    fn test_voltage_addition() {
+        assert_eq!(
+            (Voltage::from_digits(&[9, 9, 9]) + Voltage::from_digits(&[0, 0, 1])).to_string(),
+            "1000"
+        );
+        assert_eq!(Voltage::from_digits(&[0, 0]).to_string(), "0");
+    }
 
     #[test]
     fn test_example_data() {
         let example_data = "987654321111111\n811111111111119\n234234234234278\n818181911112111";
         let battery_banks = parse_battery_banks(example_data).unwrap();
 
-        assert_eq!(find_total_largest_voltage(battery_banks, 2), 357);
+        assert_eq!(
+            find_total_voltage(&battery_banks, 2, &SelectionOptions::default()).map(|voltage| voltage.to_string()),
+            Ok("357".to_string())
+        );
     }
 
     #[test]
@@ -160,8 +819,11 @@ mod tests {
         let battery_banks = parse_battery_banks(example_data).unwrap();
 
         assert_eq!(
-            battery_banks.into_iter().map(|bank| find_largest_possible_voltage(bank, 12)).collect_vec(),
-            vec![987654321111, 811111111119, 434234234278, 888911112111]
+            battery_banks
+                .iter()
+                .map(|bank| find_voltage(bank, 12, &SelectionOptions::default()).unwrap().to_string())
+                .collect_vec(),
+            vec!["987654321111", "811111111119", "434234234278", "888911112111"]
         );
     }
 }
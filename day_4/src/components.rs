@@ -0,0 +1,179 @@
+use crate::{AccessibilityRules, PaperRollRack, find_removal_depths, is_accessible};
+use itertools::Itertools;
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+/// Which rolls touch each other when grouping them into clusters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connectivity {
+    /// Rolls sharing an edge.
+    Four,
+    /// Rolls sharing an edge or a corner.
+    Eight,
+}
+
+impl Connectivity {
+    fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Connectivity::Four => &[(-1, 0), (0, -1), (0, 1), (1, 0)],
+            Connectivity::Eight => &[(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)],
+        }
+    }
+}
+
+/// Parses `4` or `8`.
+impl FromStr for Connectivity {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "4" => Ok(Connectivity::Four),
+            "8" => Ok(Connectivity::Eight),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A cluster of connected rolls.
+#[derive(Debug, PartialEq)]
+pub struct Component {
+    pub size: usize,
+    /// Top left and bottom right cell (inclusive) of the smallest box containing the whole component.
+    pub bounding_box: ((usize, usize), (usize, usize)),
+    /// Rolls that are accessible right away.
+    pub accessible_rolls: usize,
+    /// Rolls that get removed at some point when accessible rolls are removed repeatedly.
+    pub removable_rolls: usize,
+}
+
+/// Labels the connected components of the rack in scan order (by their first cell). Components never wrap around the
+/// rack edges, whatever the edge mode of the accessibility rules.
+pub fn find_components(rack: &PaperRollRack, rules: &AccessibilityRules, connectivity: Connectivity) -> Vec<Component> {
+    let removal_depths = find_removal_depths(rack, rules);
+
+    label_components(rack, connectivity)
+        .into_iter()
+        .map(|cells| {
+            let (min_row, max_row) = cells.iter().map(|(row, _)| *row).minmax().into_option().unwrap();
+            let (min_column, max_column) = cells.iter().map(|(_, column)| *column).minmax().into_option().unwrap();
+
+            Component {
+                size: cells.len(),
+                bounding_box: ((min_row, min_column), (max_row, max_column)),
+                accessible_rolls: cells
+                    .iter()
+                    .filter(|(row, column)| is_accessible(rack, *row, *column, rules))
+                    .count(),
+                removable_rolls: cells
+                    .iter()
+                    .filter(|(row, column)| removal_depths[*row][*column].is_some())
+                    .count(),
+            }
+        })
+        .collect_vec()
+}
+
+/// Groups the rolls into connected components with a breadth-first flood fill from every roll not labelled yet.
+fn label_components(rack: &PaperRollRack, connectivity: Connectivity) -> Vec<Vec<(usize, usize)>> {
+    let width = rack[0].len();
+    let height = rack.len();
+
+    let mut labelled = vec![vec![false; width]; height];
+    let mut components = Vec::new();
+
+    for (row, column) in (0..height).cartesian_product(0..width) {
+        if !rack[row][column] || labelled[row][column] {
+            continue;
+        }
+
+        let mut cells = Vec::new();
+        let mut queue = VecDeque::from([(row, column)]);
+        labelled[row][column] = true;
+
+        while let Some((row, column)) = queue.pop_front() {
+            cells.push((row, column));
+
+            for (row_offset, column_offset) in connectivity.offsets() {
+                let Some(neighbor_row) = row.checked_add_signed(*row_offset).filter(|row| *row < height) else {
+                    continue;
+                };
+                let Some(neighbor_column) = column.checked_add_signed(*column_offset).filter(|column| *column < width)
+                else {
+                    continue;
+                };
+
+                if rack[neighbor_row][neighbor_column] && !labelled[neighbor_row][neighbor_column] {
+                    labelled[neighbor_row][neighbor_column] = true;
+                    queue.push_back((neighbor_row, neighbor_column));
+                }
+            }
+        }
+
+        components.push(cells);
+    }
+
+    components
+}
+
+pub fn render_components(components: &[Component]) -> String {
+    components
+        .iter()
+        .enumerate()
+        .map(|(index, component)| {
+            let ((min_row, min_column), (max_row, max_column)) = component.bounding_box;
+
+            format!(
+                "{:>5}: {:>7} rolls, rows {min_row}-{max_row}, columns {min_column}-{max_column}, \
+                 {} accessible, {} removable",
+                index + 1,
+                component.size,
+                component.accessible_rolls,
+                component.removable_rolls
+            )
+        })
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neighborhood::{EdgeMode, Neighborhood};
+    use crate::{RackSymbols, parse_paper_rolls};
+
+    #[test]
+    fn test_connectivity() {
+        let rack = parse_paper_rolls("@.\n.@", RackSymbols::default()).unwrap();
+        let rules = AccessibilityRules::new(&Neighborhood::default(), 3, EdgeMode::Empty);
+
+        assert_eq!(find_components(&rack, &rules, Connectivity::Four).len(), 2);
+        assert_eq!(find_components(&rack, &rules, Connectivity::Eight).len(), 1);
+    }
+
+    #[test]
+    fn test_component_statistics() {
+        let rack = parse_paper_rolls("@@@..\n@@@.@\n@@@..", RackSymbols::default()).unwrap();
+
+        let rules = AccessibilityRules::new(&Neighborhood::default(), 3, EdgeMode::Empty);
+        assert_eq!(
+            find_components(&rack, &rules, Connectivity::Four),
+            vec![
+                Component {
+                    size: 9,
+                    bounding_box: ((0, 0), (2, 2)),
+                    accessible_rolls: 4,
+                    removable_rolls: 9,
+                },
+                Component {
+                    size: 1,
+                    bounding_box: ((1, 4), (1, 4)),
+                    accessible_rolls: 1,
+                    removable_rolls: 1,
+                },
+            ]
+        );
+
+        let rules = AccessibilityRules::new(&Neighborhood::default(), 2, EdgeMode::Empty);
+        let components = find_components(&rack, &rules, Connectivity::Four);
+        assert_eq!((components[0].accessible_rolls, components[0].removable_rolls), (0, 0));
+    }
+}
@@ -1,70 +1,583 @@
-use crate::PrintingDepartmentError::{EmptyInput, IllegalInput, MismatchedRowSize};
+mod components;
+mod neighborhood;
+mod packed;
+mod render;
+mod sparse;
+
+use crate::PrintingDepartmentError::{EmptyInput, IllegalInput, InvalidCoordinates, MismatchedRowSize};
+use crate::components::{Connectivity, find_components, render_components};
+use crate::neighborhood::{EdgeMode, Neighborhood, neighbor_cell};
+use crate::packed::PackedRack;
+use crate::render::{FrameExport, ImageFormat, render_removal_rounds};
+use crate::sparse::{SPARSE_DENSITY_THRESHOLD, SparseRack, fill_density};
 use itertools::Itertools;
-use std::io::{Read, stdin};
+use shared::{ArgumentError, parse_option_value, print_program_header, read_input};
+use std::collections::VecDeque;
+use std::str::FromStr;
 
 const DAY: u8 = 4;
 
 type PrintingDepartmentMultiResult<'a, T> = Result<Vec<T>, Vec<PrintingDepartmentError<'a>>>;
-type PrintingDepartmentResult<'a, T> = Result<T, PrintingDepartmentError<'a>>;
 
+/// Parse errors. Rows and columns are 1-based and refer to the input text, so blank lines count as rows too.
 #[derive(Debug, PartialEq)]
 enum PrintingDepartmentError<'a> {
-    IllegalInput(&'a str),
-    MismatchedRowSize,
+    IllegalInput {
+        line: &'a str,
+        row: usize,
+        column: usize,
+        symbol: char,
+    },
+    /// A row whose width differs from the width of the first row.
+    MismatchedRowSize {
+        row: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// A line of a coordinate list that isn't a `row,column` pair.
+    InvalidCoordinates {
+        line: &'a str,
+        row: usize,
+    },
     EmptyInput,
 }
 
+/// Characters used for the cells of the rack in the input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RackSymbols {
+    occupied: char,
+    empty: char,
+}
+
+impl Default for RackSymbols {
+    fn default() -> Self {
+        RackSymbols {
+            occupied: '@',
+            empty: '.',
+        }
+    }
+}
+
 type PaperRollRow = Vec<bool>;
 type PaperRollRack = Vec<PaperRollRow>;
 
+/// Removal round of every cell, indexed like the rack.
+type RemovalDepths = Vec<Vec<Option<usize>>>;
+
+const HISTOGRAM_WIDTH: usize = 50;
+
+/// What makes a roll accessible: having at most `max_occupied_adjacent` occupied cells at the neighborhood offsets,
+/// with cells beyond the rack edges treated according to `edge_mode`.
+#[derive(Debug, Clone, PartialEq)]
+struct AccessibilityRules {
+    offsets: Vec<(isize, isize)>,
+    max_occupied_adjacent: usize,
+    edge_mode: EdgeMode,
+}
+
+impl AccessibilityRules {
+    fn new(neighborhood: &Neighborhood, max_occupied_adjacent: usize, edge_mode: EdgeMode) -> Self {
+        AccessibilityRules {
+            offsets: neighborhood.offsets(),
+            max_occupied_adjacent,
+            edge_mode,
+        }
+    }
+}
+
+/// How the rack is stored while it's analyzed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum Representation {
+    /// Sparse for racks that are mostly empty (if the other options allow it), dense otherwise.
+    #[default]
+    Auto,
+    /// A `bool` per cell. Supports every analysis.
+    Dense,
+    /// A bit per cell, for very large racks. Only supports synchronous removal.
+    Packed,
+    /// The set of roll coordinates, for mostly empty racks. Only supports removal rounds with empty edges.
+    Sparse,
+}
+
+impl FromStr for Representation {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "auto" => Ok(Representation::Auto),
+            "dense" => Ok(Representation::Dense),
+            "packed" => Ok(Representation::Packed),
+            "sparse" => Ok(Representation::Sparse),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Arguments {
+    input_path: Option<String>,
+    neighborhood: Neighborhood,
+    max_occupied_adjacent: usize,
+    edge_mode: EdgeMode,
+    representation: Representation,
+    symbols: RackSymbols,
+    /// Read the input as one `row,column` roll per line (into a sparse rack) instead of a grid.
+    coordinates: bool,
+    depth_map: bool,
+    histogram: bool,
+    components: Option<Connectivity>,
+    render: bool,
+    frame_export: Option<FrameExport>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RemovalMode {
+    /// Each round finds all accessible rolls on a snapshot of the rack and removes them together.
+    Synchronous,
+    /// Each round removes rolls while scanning the rack, so later cells already see the earlier removals.
+    InPlace,
+}
+
+#[derive(Debug, PartialEq)]
+struct RemovalReport {
+    removed_rolls: usize,
+    /// Number of rounds that removed at least one roll.
+    rounds: usize,
+}
+
 fn main() {
-    let mut data = String::new();
-    stdin().read_to_string(&mut data).unwrap();
+    print_program_header(DAY, "Printing Department");
 
-    let rack: PaperRollRack = match parse_paper_rolls(&data) {
-        Ok(rack) => rack,
-        Err(errors) => {
-            eprintln!("Errors occurred while parsing the input: {errors:#?}");
+    let arguments = match parse_arguments(std::env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(error) => {
+            eprintln!("Invalid arguments: {error:?}");
+            eprintln!(
+                "Usage: day_4 [INPUT|-] [--neighborhood SHAPE] [--threshold N] [--edges MODE] \
+                 [--representation auto|dense|packed|sparse] [--roll-symbol C] [--empty-symbol C] [--coordinates] \
+                 [--depth-map] [--histogram] [--components 4|8] [--render] [--frames DIR] [--frame-format ppm|pgm] \
+                 [--frame-scale N]"
+            );
+            eprintln!("Neighborhood shapes: von-neumann, moore, moore:<radius>, hex, custom:<row>,<column>;...");
+            eprintln!("Edge modes: empty, wall, torus");
             return;
         }
     };
 
-    let mut padded_rack = pad_rack(rack);
-    println!("Accessible rolls (first step): {}", count_accessible_rolls(&mut padded_rack, 3, false));
+    let data = read_input(DAY, arguments.input_path.as_deref());
+    let rules = AccessibilityRules::new(&arguments.neighborhood, arguments.max_occupied_adjacent, arguments.edge_mode);
+    let representation = choose_representation(&data, &arguments);
 
-    let mut total_accessible = 0;
-    loop {
-        let extracted_rolls = count_accessible_rolls(&mut padded_rack, 3, true);
-        total_accessible += extracted_rolls;
+    // Sparse racks are parsed straight into roll coordinates, so they never need a cell per position.
+    if representation == Representation::Sparse {
+        let parsed = if arguments.coordinates {
+            parse_roll_coordinates(&data)
+        } else {
+            parse_sparse_paper_rolls(&data, arguments.symbols)
+        };
+
+        match parsed {
+            Ok(rack) => report_sparse_rack(rack, &rules),
+            Err(errors) => eprintln!("Errors occurred while parsing the input: {errors:#?}"),
+        }
+        return;
+    }
+
+    // Packed racks are built a row at a time as well, so only one row of cells exists at once.
+    if representation == Representation::Packed {
+        match parse_packed_paper_rolls(&data, arguments.symbols) {
+            Ok(rack) => report_packed_rack(rack, &rules),
+            Err(errors) => eprintln!("Errors occurred while parsing the input: {errors:#?}"),
+        }
+        return;
+    }
+
+    match parse_paper_rolls(&data, arguments.symbols) {
+        Ok(rack) => report_dense_rack(rack, &rules, &arguments),
+        Err(errors) => eprintln!("Errors occurred while parsing the input: {errors:#?}"),
+    }
+}
+
+/// Resolves `Representation::Auto`: sparse for coordinate lists, and for racks filled below
+/// `SPARSE_DENSITY_THRESHOLD` when nothing requires a dense rack, dense otherwise.
+fn choose_representation(data: &str, arguments: &Arguments) -> Representation {
+    if arguments.coordinates {
+        return Representation::Sparse;
+    }
+
+    if arguments.representation != Representation::Auto {
+        return arguments.representation;
+    }
+
+    let needs_dense_rack = arguments.edge_mode != EdgeMode::Empty
+        || arguments.depth_map
+        || arguments.histogram
+        || arguments.components.is_some()
+        || arguments.render
+        || arguments.frame_export.is_some();
+
+    if !needs_dense_rack && fill_density(data, arguments.symbols) < SPARSE_DENSITY_THRESHOLD {
+        Representation::Sparse
+    } else {
+        Representation::Dense
+    }
+}
+
+fn report_dense_rack(mut rack: PaperRollRack, rules: &AccessibilityRules, arguments: &Arguments) {
+    println!("Accessible rolls (first step): {}", count_accessible_rolls(&mut rack, rules, false));
+
+    for (label, mode) in [("synchronous", RemovalMode::Synchronous), ("in-place", RemovalMode::InPlace)] {
+        let report = run_removal_rounds(&mut rack.clone(), rules, mode);
+        println!("Removal rounds ({label}): {} rounds, {} rolls", report.rounds, report.removed_rolls);
+    }
+
+    if arguments.render || arguments.frame_export.is_some() {
+        println!();
+        if let Err(error) = render_removal_rounds(&rack, rules, arguments.render, arguments.frame_export.as_ref()) {
+            eprintln!("Could not write the frames: {error}");
+        }
+    }
+
+    if arguments.depth_map || arguments.histogram {
+        let removal_depths = find_removal_depths(&rack, rules);
+
+        if arguments.depth_map {
+            println!();
+            println!("Removal rounds per roll:");
+            println!("{}", render_depth_map(&rack, &removal_depths));
+        }
+
+        if arguments.histogram {
+            println!();
+            println!("Rolls removed per round:");
+            println!("{}", render_histogram(&removal_histogram(&removal_depths)));
+        }
+    }
+
+    if let Some(connectivity) = arguments.components {
+        let components = find_components(&rack, rules, connectivity);
+        let connectivity_label = match connectivity {
+            Connectivity::Four => 4,
+            Connectivity::Eight => 8,
+        };
+
+        println!();
+        println!("Connected components ({connectivity_label}-connectivity): {}", components.len());
+        println!("{}", render_components(&components));
+    }
+
+    if arguments.depth_map || arguments.histogram || arguments.components.is_some() {
+        println!();
+    }
+
+    let total_accessible = remove_all_accessible_rolls(&mut rack, rules);
+    println!("Accessible rolls (repeated): {total_accessible}")
+}
+
+fn report_packed_rack(mut rack: PackedRack, rules: &AccessibilityRules) {
+    println!("Accessible rolls (first step): {}", rack.count_accessible_rolls(rules));
+
+    let report = rack.run_removal_rounds(rules);
+    println!("Removal rounds (synchronous): {} rounds, {} rolls", report.rounds, report.removed_rolls);
+    println!("Accessible rolls (repeated): {}", report.removed_rolls);
+    println!("Rolls left: {}", rack.count_rolls());
+}
+
+/// Prints the same report as `report_dense_rack` does without any of the dense-only options.
+fn report_sparse_rack(rack: SparseRack, rules: &AccessibilityRules) {
+    println!("Accessible rolls (first step): {}", rack.count_accessible_rolls(rules));
+
+    let synchronous_report = rack.clone().run_removal_rounds(rules, RemovalMode::Synchronous);
+    let in_place_report = rack.clone().run_removal_rounds(rules, RemovalMode::InPlace);
+
+    for (label, report) in [("synchronous", &synchronous_report), ("in-place", &in_place_report)] {
+        println!("Removal rounds ({label}): {} rounds, {} rolls", report.rounds, report.removed_rolls);
+    }
+
+    println!("Accessible rolls (repeated): {}", synchronous_report.removed_rolls)
+}
+
+fn parse_arguments(mut args: impl Iterator<Item = String>) -> Result<Arguments, ArgumentError> {
+    let mut arguments = Arguments {
+        input_path: None,
+        neighborhood: Neighborhood::default(),
+        max_occupied_adjacent: 3,
+        edge_mode: EdgeMode::default(),
+        representation: Representation::default(),
+        symbols: RackSymbols::default(),
+        coordinates: false,
+        depth_map: false,
+        histogram: false,
+        components: None,
+        render: false,
+        frame_export: None,
+    };
+    let mut frame_directory = None;
+    let mut frame_format = ImageFormat::default();
+    let mut frame_scale = 1;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--neighborhood" => arguments.neighborhood = parse_option_value(&arg, &mut args)?,
+            "--threshold" => arguments.max_occupied_adjacent = parse_option_value(&arg, &mut args)?,
+            "--edges" => arguments.edge_mode = parse_option_value(&arg, &mut args)?,
+            "--representation" => arguments.representation = parse_option_value(&arg, &mut args)?,
+            "--roll-symbol" => arguments.symbols.occupied = parse_option_value(&arg, &mut args)?,
+            "--empty-symbol" => arguments.symbols.empty = parse_option_value(&arg, &mut args)?,
+            "--coordinates" => arguments.coordinates = true,
+            "--depth-map" => arguments.depth_map = true,
+            "--histogram" => arguments.histogram = true,
+            "--components" => arguments.components = Some(parse_option_value(&arg, &mut args)?),
+            "--render" => arguments.render = true,
+            "--frames" => frame_directory = Some(parse_option_value(&arg, &mut args)?),
+            "--frame-format" => frame_format = parse_option_value(&arg, &mut args)?,
+            "--frame-scale" => frame_scale = parse_option_value(&arg, &mut args)?,
+            option if option.starts_with("--") => return Err(ArgumentError::UnknownOption(arg)),
+            _ if arguments.input_path.is_none() => arguments.input_path = Some(arg),
+            _ => return Err(ArgumentError::UnexpectedArgument(arg)),
+        }
+    }
+
+    if arguments.symbols.occupied == arguments.symbols.empty {
+        return Err(ArgumentError::ConflictingOptions("--roll-symbol".to_string(), "--empty-symbol".to_string()));
+    }
+
+    if frame_scale == 0 {
+        return Err(ArgumentError::InvalidValue {
+            option: "--frame-scale".to_string(),
+            value: frame_scale.to_string(),
+        });
+    }
+
+    arguments.frame_export = frame_directory.map(|directory| FrameExport {
+        directory,
+        format: frame_format,
+        scale: frame_scale,
+    });
+
+    // Coordinate lists can only be stored sparsely.
+    let representation_option = if arguments.coordinates {
+        if matches!(arguments.representation, Representation::Dense | Representation::Packed) {
+            return Err(ArgumentError::ConflictingOptions("--coordinates".to_string(), "--representation".to_string()));
+        }
+
+        "--coordinates"
+    } else {
+        "--representation"
+    };
+    let sparse = arguments.coordinates || arguments.representation == Representation::Sparse;
+
+    if sparse && arguments.edge_mode != EdgeMode::Empty {
+        return Err(ArgumentError::ConflictingOptions(representation_option.to_string(), "--edges".to_string()));
+    }
+
+    if sparse || arguments.representation == Representation::Packed {
+        let dense_only_options = [
+            ("--depth-map", arguments.depth_map),
+            ("--histogram", arguments.histogram),
+            ("--components", arguments.components.is_some()),
+            ("--render", arguments.render),
+            ("--frames", arguments.frame_export.is_some()),
+        ];
+
+        for (option, used) in dense_only_options {
+            if used {
+                return Err(ArgumentError::ConflictingOptions(representation_option.to_string(), option.to_string()));
+            }
+        }
+    }
+
+    Ok(arguments)
+}
 
+/// Removes accessible rolls round by round until a round finds none.
+fn run_removal_rounds(
+    rack: &mut PaperRollRack,
+    rules: &AccessibilityRules,
+    mode: RemovalMode,
+) -> RemovalReport {
+    let mut report = RemovalReport { removed_rolls: 0, rounds: 0 };
+
+    loop {
+        let extracted_rolls = remove_accessible_rolls(rack, rules, mode);
         if extracted_rolls == 0 {
             break;
         }
+
+        report.removed_rolls += extracted_rolls;
+        report.rounds += 1;
     }
 
-    println!("Accessible rolls (repeated): {total_accessible}")
+    report
 }
 
-fn count_accessible_rolls(
-    padded_rack: &mut PaperRollRack,
-    max_occupied_adjacent: usize,
-    extract: bool,
-) -> usize {
+/// Runs a single removal round and returns the number of removed rolls.
+fn remove_accessible_rolls(rack: &mut PaperRollRack, rules: &AccessibilityRules, mode: RemovalMode) -> usize {
+    match mode {
+        RemovalMode::InPlace => count_accessible_rolls(rack, rules, true),
+        RemovalMode::Synchronous => {
+            let accessible_rolls = find_accessible_rolls(rack, rules);
+            for (row, column) in &accessible_rolls {
+                rack[*row][*column] = false;
+            }
+
+            accessible_rolls.len()
+        }
+    }
+}
+
+fn find_accessible_rolls(rack: &PaperRollRack, rules: &AccessibilityRules) -> Vec<(usize, usize)> {
+    let width = rack[0].len();
+    let height = rack.len();
+
+    (0..height)
+        .cartesian_product(0..width)
+        .filter(|(row, column)| rack[*row][*column])
+        .filter(|(row, column)| is_accessible(rack, *row, *column, rules))
+        .collect_vec()
+}
+
+/// Repeatedly removes accessible rolls until none are left, returning how many were removed in total.
+fn remove_all_accessible_rolls(rack: &mut PaperRollRack, rules: &AccessibilityRules) -> usize {
+    let removal_depths = find_removal_depths(rack, rules);
+    let mut removed_rolls = 0;
+
+    for (row, depths) in removal_depths.iter().enumerate() {
+        for (column, depth) in depths.iter().enumerate() {
+            if depth.is_some() {
+                rack[row][column] = false;
+                removed_rolls += 1;
+            }
+        }
+    }
+
+    removed_rolls
+}
+
+/// Finds the (synchronous) round in which every roll gets removed, starting at round 1 for the rolls that are
+/// accessible right away. Rolls that never become accessible (and empty cells) have no depth.
+///
+/// Instead of rescanning the whole rack after every removal, this keeps the occupied neighbor count of every roll and
+/// a queue of rolls that are known to be accessible. Removing a roll only updates its neighbors, and a neighbor is
+/// queued the moment its count drops to `max_occupied_adjacent`, so every roll is queued at most once. Since the
+/// queue is processed in order, a roll queued by the removal of a round `n` roll becomes accessible in round `n + 1`.
+fn find_removal_depths(rack: &PaperRollRack, rules: &AccessibilityRules) -> RemovalDepths {
+    let width = rack[0].len();
+    let height = rack.len();
+
+    let mut remaining_rack = rack.clone();
+    let mut neighbor_counts = vec![vec![0; width]; height];
+    let mut removal_depths = vec![vec![None; width]; height];
+    let mut queue = VecDeque::new();
+
+    for row in 0..height {
+        for column in 0..width {
+            if !rack[row][column] {
+                continue;
+            }
+
+            let occupied_neighbor_count = find_occupied_neighbor_count(rack, row, column, rules);
+            neighbor_counts[row][column] = occupied_neighbor_count;
+
+            if occupied_neighbor_count <= rules.max_occupied_adjacent {
+                removal_depths[row][column] = Some(1);
+                queue.push_back((row, column));
+            }
+        }
+    }
+
+    while let Some((row, column)) = queue.pop_front() {
+        remaining_rack[row][column] = false;
+        let next_depth = removal_depths[row][column].map(|depth| depth + 1);
+
+        // The removed roll is a neighbor of every cell it can be reached from, i.e. the ones at the negated offsets.
+        for (row_offset, column_offset) in &rules.offsets {
+            let reverse_offset = (-row_offset, -column_offset);
+            let Some((neighbor_row, neighbor_column)) =
+                neighbor_cell((height, width), (row, column), reverse_offset, rules.edge_mode)
+            else {
+                continue;
+            };
+
+            if !remaining_rack[neighbor_row][neighbor_column] {
+                continue;
+            }
+
+            let neighbor_count = &mut neighbor_counts[neighbor_row][neighbor_column];
+            *neighbor_count -= 1;
+
+            if *neighbor_count == rules.max_occupied_adjacent {
+                removal_depths[neighbor_row][neighbor_column] = next_depth;
+                queue.push_back((neighbor_row, neighbor_column));
+            }
+        }
+    }
+
+    removal_depths
+}
+
+/// Counts the removed rolls per round, the first entry being round 1.
+fn removal_histogram(removal_depths: &RemovalDepths) -> Vec<usize> {
+    let mut histogram = Vec::new();
+
+    for depth in removal_depths.iter().flatten().flatten() {
+        if histogram.len() < *depth {
+            histogram.resize(*depth, 0);
+        }
+
+        histogram[depth - 1] += 1;
+    }
+
+    histogram
+}
+
+/// Draws the rack with every roll replaced by its removal round (`1`-`9`, then `a`-`z`, `+` for anything later).
+/// Rolls that are never removed are drawn as `#`.
+fn render_depth_map(rack: &PaperRollRack, removal_depths: &RemovalDepths) -> String {
+    let width = rack[0].len();
+    let height = rack.len();
+
+    (0..height)
+        .map(|row| {
+            (0..width)
+                .map(|column| match (rack[row][column], removal_depths[row][column]) {
+                    (false, _) => '.',
+                    (true, None) => '#',
+                    (true, Some(depth)) => char::from_digit(depth as u32, 36).unwrap_or('+'),
+                })
+                .collect::<String>()
+        })
+        .join("\n")
+}
+
+fn render_histogram(histogram: &[usize]) -> String {
+    let max_count = histogram.iter().copied().max().unwrap_or(0).max(1);
+
+    histogram
+        .iter()
+        .enumerate()
+        .map(|(round, count)| {
+            let bar = "#".repeat(count * HISTOGRAM_WIDTH / max_count);
+            format!("{:>5}: {count:>7} {bar}", round + 1)
+        })
+        .join("\n")
+}
+
+fn count_accessible_rolls(rack: &mut PaperRollRack, rules: &AccessibilityRules, extract: bool) -> usize {
     let mut accessible_rolls = 0;
 
-    let width = padded_rack[0].len() - 2;
-    let height = padded_rack.len() - 2;
+    let width = rack[0].len();
+    let height = rack.len();
 
-    for row in 1..=height {
-        for column in 1..=width {
-            if padded_rack[row][column] == false {
+    for row in 0..height {
+        for column in 0..width {
+            if !rack[row][column] {
                 continue;
             }
 
-            let occupied_neighbor_count = find_occupied_neighbor_count(&padded_rack, row, column);
-            if occupied_neighbor_count <= max_occupied_adjacent {
+            if is_accessible(rack, row, column, rules) {
                 if extract {
-                    padded_rack[row][column] = false;
+                    rack[row][column] = false;
                 }
 
                 accessible_rolls += 1;
@@ -75,74 +588,142 @@ fn count_accessible_rolls(
     accessible_rolls
 }
 
-fn find_occupied_neighbor_count(padded_rack: &PaperRollRack, row: usize, column: usize) -> usize {
-    let mut occupied_neighbors = 0;
+fn is_accessible(rack: &PaperRollRack, row: usize, column: usize, rules: &AccessibilityRules) -> bool {
+    find_occupied_neighbor_count(rack, row, column, rules) <= rules.max_occupied_adjacent
+}
 
-    for check_row in (row - 1)..=(row + 1) {
-        for check_column in (column - 1)..=(column + 1) {
-            let cell_occupied = padded_rack[check_row][check_column];
+fn find_occupied_neighbor_count(rack: &PaperRollRack, row: usize, column: usize, rules: &AccessibilityRules) -> usize {
+    let size = (rack.len(), rack[0].len());
 
-            if cell_occupied && (check_row != row || check_column != column) {
-                occupied_neighbors += 1;
-            }
-        }
-    }
+    rules
+        .offsets
+        .iter()
+        .filter(|offset| match neighbor_cell(size, (row, column), **offset, rules.edge_mode) {
+            Some((neighbor_row, neighbor_column)) => rack[neighbor_row][neighbor_column],
+            None => rules.edge_mode == EdgeMode::Wall,
+        })
+        .count()
+}
 
-    occupied_neighbors
+fn parse_paper_rolls(data: &str, symbols: RackSymbols) -> PrintingDepartmentMultiResult<'_, PaperRollRow> {
+    parse_rack_rows(data, symbols, |_, cells| cells)
 }
 
-fn pad_rack(mut rack: PaperRollRack) -> PaperRollRack {
-    rack.iter_mut().for_each(|row| {
-        row.insert(0, false);
-        row.push(false);
-    });
+/// Parses the rack straight into the coordinates of its rolls, without keeping a cell for every position.
+fn parse_sparse_paper_rolls(data: &str, symbols: RackSymbols) -> Result<SparseRack, Vec<PrintingDepartmentError<'_>>> {
+    let rows = parse_rack_rows(data, symbols, |row, cells| {
+        cells
+            .into_iter()
+            .positions(|occupied| occupied)
+            .map(|column| (row as i64, column as i64))
+            .collect_vec()
+    })?;
 
-    let row_len = rack[0].len();
+    Ok(rows.into_iter().flatten().collect())
+}
 
-    let mut padding_top_bottom_row = Vec::new();
-    padding_top_bottom_row.resize(row_len, false);
+/// Parses the rack straight into its bits, packing every row as soon as it has been checked.
+fn parse_packed_paper_rolls(data: &str, symbols: RackSymbols) -> Result<PackedRack, Vec<PrintingDepartmentError<'_>>> {
+    let mut rack: Option<PackedRack> = None;
 
-    rack.insert(0, padding_top_bottom_row.clone());
-    rack.push(padding_top_bottom_row.clone());
+    parse_rack_rows(data, symbols, |_, cells| {
+        rack.get_or_insert_with(|| PackedRack::with_width(cells.len())).push_row(&cells)
+    })?;
 
-    rack
+    // Parsing only succeeds with at least one row.
+    Ok(rack.expect("The rack has rows"))
 }
 
-fn parse_paper_rolls(data: &str) -> PrintingDepartmentMultiResult<'_, PaperRollRow> {
-    let (rows, errors): (Vec<_>, Vec<_>) = data
+/// Checks the rows of the rack and hands the cells of each one to `convert_row`, along with its (0-based) row index
+/// in the rack.
+fn parse_rack_rows<'a, T>(
+    data: &'a str,
+    symbols: RackSymbols,
+    mut convert_row: impl FnMut(usize, PaperRollRow) -> T,
+) -> PrintingDepartmentMultiResult<'a, T> {
+    // Whitespace only makes a line blank if it isn't used for the cells, as rows of empty cells may look blank too.
+    let is_blank = |line: &str| {
+        line.chars().all(|symbol| symbol.is_whitespace() && symbol != symbols.occupied && symbol != symbols.empty)
+    };
+
+    let lines = data
         .lines()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| parse_input_row(line))
+        .enumerate()
+        .filter(|(_, line)| !is_blank(line))
+        .map(|(index, line)| (index + 1, line))
+        .collect_vec();
+
+    let Some((_, first_line)) = lines.first() else {
+        return Err(vec![EmptyInput]);
+    };
+    let expected_width = first_line.chars().count();
+
+    let (rows, errors): (Vec<_>, Vec<_>) = lines
+        .iter()
+        .enumerate()
+        .map(|(index, (row, line))| parse_input_row(line, *row, symbols).map(|cells| convert_row(index, cells)))
         .partition_result();
 
+    let mismatched_rows = lines
+        .iter()
+        .map(|(row, line)| (*row, line.chars().count()))
+        .filter(|(_, width)| *width != expected_width)
+        .map(|(row, width)| MismatchedRowSize {
+            row,
+            expected: expected_width,
+            actual: width,
+        });
+
+    let errors = errors.into_iter().flatten().chain(mismatched_rows).collect_vec();
     if !errors.is_empty() {
         return Err(errors);
     }
 
-    let row_length_count = rows.iter().map(|row| row.len()).unique().count();
-    if row_length_count != 1 {
-        return Err(vec![MismatchedRowSize]);
+    Ok(rows)
+}
+
+/// Parses one `row,column` roll per line. Coordinates may be negative, so rolls aren't limited to a grid.
+fn parse_roll_coordinates(data: &str) -> Result<SparseRack, Vec<PrintingDepartmentError<'_>>> {
+    let (rolls, errors): (Vec<_>, Vec<_>) = data
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            line.split_once(',')
+                .and_then(|(row, column)| Some((row.trim().parse().ok()?, column.trim().parse().ok()?)))
+                .ok_or(InvalidCoordinates { line, row: index + 1 })
+        })
+        .partition_result();
+
+    if !errors.is_empty() {
+        return Err(errors);
     }
 
-    if rows.is_empty() {
+    if rolls.is_empty() {
         return Err(vec![EmptyInput]);
     }
 
-    Ok(rows)
+    Ok(rolls.into_iter().collect())
 }
 
-fn parse_input_row(line: &str) -> PrintingDepartmentResult<'_, PaperRollRow> {
+fn parse_input_row(line: &str, row: usize, symbols: RackSymbols) -> PrintingDepartmentMultiResult<'_, bool> {
     let (rolls, errors): (Vec<_>, Vec<_>) = line
         .chars()
-        .map(|c| match c {
-            '@' => Ok(true),
-            '.' => Ok(false),
-            _ => Err(IllegalInput(line)),
+        .enumerate()
+        .map(|(index, symbol)| match symbol {
+            _ if symbol == symbols.occupied => Ok(true),
+            _ if symbol == symbols.empty => Ok(false),
+            _ => Err(IllegalInput {
+                line,
+                row,
+                column: index + 1,
+                symbol,
+            }),
         })
         .partition_result();
 
     if !errors.is_empty() {
-        return Err(IllegalInput(line));
+        return Err(errors);
     }
 
     Ok(rolls)
@@ -152,10 +733,23 @@ fn parse_input_row(line: &str) -> PrintingDepartmentResult<'_, PaperRollRow> {
 mod tests {
     use super::*;
 
+    fn default_rules() -> AccessibilityRules {
+        AccessibilityRules::new(&Neighborhood::default(), 3, EdgeMode::Empty)
+    }
+
+    pub(crate) const EXAMPLE_INPUT: &str = "..@@.@@@@.\n@@@.@.@.@@\n@@@@@.@.@@\n@.@@@@..@.\n@@.@@@@.@@\n.@@@@@@@.@\n.@.@.@.@@@\n@.@@@.@@@@\n.@@@@@@@@.\n@.@.@@@.@.";
+
+    /// Advances the state of a small linear congruential generator and returns it, for reproducible random tests.
+    /// The low bits repeat quickly, so callers should take the high bits.
+    pub(crate) fn next_random(state: &mut u64) -> u64 {
+        *state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        *state
+    }
+
     #[test]
     fn test_parse_rolls_valid() {
         assert_eq!(
-            parse_paper_rolls("@.@.\n@@..\n@..@\n\n.@@."),
+            parse_paper_rolls("@.@.\n@@..\n@..@\n\n.@@.", RackSymbols::default()),
             Ok(vec![
                 vec![true, false, true, false],
                 vec![true, true, false, false],
@@ -168,50 +762,154 @@ mod tests {
     #[test]
     fn test_parse_rolls_illegal_chars() {
         assert_eq!(
-            parse_paper_rolls("@.@a\n@@..\n@  @\n\n.@@."),
-            Err(vec![IllegalInput("@.@a"), IllegalInput("@  @"),])
+            parse_paper_rolls("@.@a\n@@..\n@  @\n\n.@@.", RackSymbols::default()),
+            Err(vec![
+                IllegalInput {
+                    line: "@.@a",
+                    row: 1,
+                    column: 4,
+                    symbol: 'a'
+                },
+                IllegalInput {
+                    line: "@  @",
+                    row: 3,
+                    column: 2,
+                    symbol: ' '
+                },
+                IllegalInput {
+                    line: "@  @",
+                    row: 3,
+                    column: 3,
+                    symbol: ' '
+                },
+            ])
         )
     }
 
     #[test]
     fn test_parse_rolls_mismatched_row_size() {
         assert_eq!(
-            parse_paper_rolls("@@@@\n@@@\n@@@@"),
-            Err(vec![MismatchedRowSize])
+            parse_paper_rolls("@@@@\n@@@\n\n@@@@@", RackSymbols::default()),
+            Err(vec![
+                MismatchedRowSize {
+                    row: 2,
+                    expected: 4,
+                    actual: 3
+                },
+                MismatchedRowSize {
+                    row: 4,
+                    expected: 4,
+                    actual: 5
+                },
+            ])
         )
     }
 
     #[test]
-    fn test_pad_rack() {
+    fn test_parse_rolls_empty_input() {
+        assert_eq!(parse_paper_rolls("", RackSymbols::default()), Err(vec![EmptyInput]));
+        assert_eq!(parse_paper_rolls("\n  \n", RackSymbols::default()), Err(vec![EmptyInput]));
+    }
+
+    #[test]
+    fn test_parse_rolls_custom_symbols() {
+        let symbols = RackSymbols {
+            occupied: '#',
+            empty: ' ',
+        };
+
         assert_eq!(
-            pad_rack(vec![vec![true, true], vec![true, true]]),
-            vec![
-                vec![false, false, false, false],
-                vec![false, true, true, false],
-                vec![false, true, true, false],
-                vec![false, false, false, false],
-            ]
+            parse_paper_rolls("# #\n## ", symbols),
+            Ok(vec![vec![true, false, true], vec![true, true, false]])
+        );
+        assert_eq!(
+            parse_paper_rolls("# #\n   \n\n  #", symbols),
+            Ok(vec![vec![true, false, true], vec![false, false, false], vec![false, false, true]])
+        );
+        assert_eq!(
+            parse_paper_rolls("#@", symbols),
+            Err(vec![IllegalInput {
+                line: "#@",
+                row: 1,
+                column: 2,
+                symbol: '@'
+            }])
+        );
+    }
+
+    #[test]
+    fn test_parse_roll_coordinates() {
+        let rack = parse_roll_coordinates("0,0\n -3, 4\n\n-1099511627776,7").unwrap();
+
+        assert_eq!(rack, SparseRack::from_iter([(0, 0), (-3, 4), (-1 << 40, 7)]));
+        assert_eq!(
+            parse_roll_coordinates("1,2\n3\n4,x"),
+            Err(vec![
+                InvalidCoordinates { line: "3", row: 2 },
+                InvalidCoordinates { line: "4,x", row: 3 },
+            ])
+        );
+        assert_eq!(parse_roll_coordinates("\n"), Err(vec![EmptyInput]));
+    }
+
+    #[test]
+    fn test_parse_sparse_rolls() {
+        assert_eq!(
+            parse_sparse_paper_rolls("@..\n\n..@", RackSymbols::default()),
+            Ok(SparseRack::from_iter([(0, 0), (1, 2)]))
+        );
+        assert_eq!(
+            parse_sparse_paper_rolls("@..\n.@", RackSymbols::default()),
+            Err(vec![MismatchedRowSize {
+                row: 2,
+                expected: 3,
+                actual: 2
+            }])
+        );
+    }
+
+    #[test]
+    fn test_parse_packed_rolls() {
+        let mut expected = PackedRack::with_width(3);
+        expected.push_row(&[true, false, false]);
+        expected.push_row(&[false, false, true]);
+
+        assert_eq!(parse_packed_paper_rolls("@..\n\n..@", RackSymbols::default()), Ok(expected));
+        assert_eq!(
+            parse_packed_paper_rolls("@..\n.@", RackSymbols::default()),
+            Err(vec![MismatchedRowSize {
+                row: 2,
+                expected: 3,
+                actual: 2
+            }])
         );
     }
 
+    #[test]
+    fn test_choose_representation() {
+        let arguments = |args: &[&str]| parse_arguments(args.iter().map(|arg| arg.to_string())).unwrap();
+        let sparse_data = format!("@{}", ".".repeat(99));
+
+        assert_eq!(choose_representation(&sparse_data, &arguments(&[])), Representation::Sparse);
+        assert_eq!(choose_representation(&sparse_data, &arguments(&["--histogram"])), Representation::Dense);
+        assert_eq!(choose_representation(EXAMPLE_INPUT, &arguments(&[])), Representation::Dense);
+        assert_eq!(choose_representation("1,2", &arguments(&["--coordinates"])), Representation::Sparse);
+    }
+
     #[test]
     fn test_example_data() {
-        let example_data = "..@@.@@@@.\n@@@.@.@.@@\n@@@@@.@.@@\n@.@@@@..@.\n@@.@@@@.@@\n.@@@@@@@.@\n.@.@.@.@@@\n@.@@@.@@@@\n.@@@@@@@@.\n@.@.@@@.@.";
-        let mut padded_rack = pad_rack(parse_paper_rolls(&example_data).unwrap());
+        let mut rack = parse_paper_rolls(EXAMPLE_INPUT, RackSymbols::default()).unwrap();
 
-        assert_eq!(count_accessible_rolls(&mut padded_rack, 3, false), 13);
+        assert_eq!(count_accessible_rolls(&mut rack, &default_rules(), false), 13);
     }
 
     #[test]
     fn test_example_data_part_2() {
-        let example_data = "..@@.@@@@.\n@@@.@.@.@@\n@@@@@.@.@@\n@.@@@@..@.\n@@.@@@@.@@\n.@@@@@@@.@\n.@.@.@.@@@\n@.@@@.@@@@\n.@@@@@@@@.\n@.@.@@@.@.";
-        let rack = parse_paper_rolls(&example_data).unwrap();
-
-        let mut padded_rack = pad_rack(rack);
+        let mut rack = parse_paper_rolls(EXAMPLE_INPUT, RackSymbols::default()).unwrap();
         let mut total_accessible = 0;
 
         loop {
-            let extracted_rolls = count_accessible_rolls(&mut padded_rack, 3, true);
+            let extracted_rolls = count_accessible_rolls(&mut rack, &default_rules(), true);
             total_accessible += extracted_rolls;
 
             if extracted_rolls == 0 {
@@ -221,4 +919,248 @@ mod tests {
 
         assert_eq!(total_accessible, 43);
     }
+
+    #[test]
+    fn test_example_data_part_2_worklist() {
+        let mut rack = parse_paper_rolls(EXAMPLE_INPUT, RackSymbols::default()).unwrap();
+
+        assert_eq!(remove_all_accessible_rolls(&mut rack, &default_rules()), 43);
+        assert_eq!(count_accessible_rolls(&mut rack, &default_rules(), false), 0);
+    }
+
+    #[test]
+    fn test_worklist_matches_repeated_sweeps() {
+        let example_data = "@@@@@\n@@.@@\n@@@@@\n.@@@.\n@@@@@";
+
+        for max_occupied_adjacent in 0..=8 {
+            let rules = AccessibilityRules::new(&Neighborhood::default(), max_occupied_adjacent, EdgeMode::Empty);
+            let mut swept_rack = parse_paper_rolls(example_data, RackSymbols::default()).unwrap();
+            let mut worklist_rack = swept_rack.clone();
+
+            let mut total_swept = 0;
+            loop {
+                let extracted_rolls = count_accessible_rolls(&mut swept_rack, &rules, true);
+                total_swept += extracted_rolls;
+
+                if extracted_rolls == 0 {
+                    break;
+                }
+            }
+
+            assert_eq!(remove_all_accessible_rolls(&mut worklist_rack, &rules), total_swept);
+            assert_eq!(worklist_rack, swept_rack);
+        }
+    }
+
+    #[test]
+    fn test_removal_modes_example_data() {
+        let rack = parse_paper_rolls(EXAMPLE_INPUT, RackSymbols::default()).unwrap();
+
+        assert_eq!(
+            run_removal_rounds(&mut rack.clone(), &default_rules(), RemovalMode::Synchronous),
+            RemovalReport { removed_rolls: 43, rounds: 9 }
+        );
+        assert_eq!(
+            run_removal_rounds(&mut rack.clone(), &default_rules(), RemovalMode::InPlace),
+            RemovalReport { removed_rolls: 43, rounds: 3 }
+        );
+    }
+
+    #[test]
+    fn test_removal_modes_scan_order() {
+        let rack = parse_paper_rolls("@@@@@", RackSymbols::default()).unwrap();
+        let rules = AccessibilityRules::new(&Neighborhood::default(), 1, EdgeMode::Empty);
+
+        // Scanning left to right, every roll has a single neighbor left by the time it's checked.
+        assert_eq!(
+            run_removal_rounds(&mut rack.clone(), &rules, RemovalMode::InPlace),
+            RemovalReport { removed_rolls: 5, rounds: 1 }
+        );
+
+        // From a snapshot, only the two ends are accessible in each round.
+        assert_eq!(
+            run_removal_rounds(&mut rack.clone(), &rules, RemovalMode::Synchronous),
+            RemovalReport { removed_rolls: 5, rounds: 3 }
+        );
+    }
+
+    #[test]
+    fn test_removal_depths() {
+        let rack = parse_paper_rolls("@@@@@\n@@@@@\n@@@@@\n.....\n@@@..", RackSymbols::default()).unwrap();
+        let removal_depths = find_removal_depths(&rack, &default_rules());
+
+        assert_eq!(
+            render_depth_map(&rack, &removal_depths),
+            "13431\n24542\n13431\n.....\n111.."
+        );
+        assert_eq!(removal_histogram(&removal_depths), vec![7, 2, 4, 4, 1]);
+
+        let rack = parse_paper_rolls("@@@..\n@@@.@", RackSymbols::default()).unwrap();
+        let rules = AccessibilityRules::new(&Neighborhood::default(), 2, EdgeMode::Empty);
+        let removal_depths = find_removal_depths(&rack, &rules);
+
+        assert_eq!(render_depth_map(&rack, &removal_depths), "###..\n###.1");
+        assert_eq!(removal_histogram(&removal_depths), vec![1]);
+    }
+
+    #[test]
+    fn test_removal_depths_match_synchronous_rounds() {
+        let rack = parse_paper_rolls(EXAMPLE_INPUT, RackSymbols::default()).unwrap();
+        let histogram = removal_histogram(&find_removal_depths(&rack, &default_rules()));
+
+        assert_eq!(histogram, vec![13, 12, 7, 5, 2, 1, 1, 1, 1]);
+
+        let mut synchronous_rack = rack.clone();
+        for removed_rolls in histogram {
+            assert_eq!(
+                remove_accessible_rolls(&mut synchronous_rack, &default_rules(), RemovalMode::Synchronous),
+                removed_rolls
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        let args = ["--neighborhood", "moore:2", "--threshold", "5", "--edges", "torus", "rack.txt"];
+
+        assert_eq!(
+            parse_arguments(args.into_iter().map(String::from)),
+            Ok(Arguments {
+                input_path: Some("rack.txt".to_string()),
+                neighborhood: Neighborhood::Moore { radius: 2 },
+                max_occupied_adjacent: 5,
+                edge_mode: EdgeMode::Toroidal,
+                representation: Representation::Auto,
+                symbols: RackSymbols::default(),
+                coordinates: false,
+                depth_map: false,
+                histogram: false,
+                components: None,
+                render: false,
+                frame_export: None,
+            })
+        );
+        assert_eq!(
+            parse_arguments(["--frames", "frames", "--frame-format", "pgm"].into_iter().map(String::from))
+                .map(|arguments| arguments.frame_export),
+            Ok(Some(FrameExport {
+                directory: "frames".to_string(),
+                format: ImageFormat::Pgm,
+                scale: 1,
+            }))
+        );
+        assert_eq!(
+            parse_arguments(["--representation", "packed", "--histogram"].into_iter().map(String::from)),
+            Err(ArgumentError::ConflictingOptions(
+                "--representation".to_string(),
+                "--histogram".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_arguments(["--representation", "sparse", "--edges", "wall"].into_iter().map(String::from)),
+            Err(ArgumentError::ConflictingOptions(
+                "--representation".to_string(),
+                "--edges".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_arguments(["--coordinates", "--representation", "dense"].into_iter().map(String::from)),
+            Err(ArgumentError::ConflictingOptions(
+                "--coordinates".to_string(),
+                "--representation".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_arguments(["--coordinates", "--depth-map"].into_iter().map(String::from)),
+            Err(ArgumentError::ConflictingOptions(
+                "--coordinates".to_string(),
+                "--depth-map".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_arguments(["--neighborhood", "ring"].into_iter().map(String::from)),
+            Err(ArgumentError::InvalidValue {
+                option: "--neighborhood".to_string(),
+                value: "ring".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_neighborhood_shapes() {
+        let rack = "@@@\n@@@\n@@@";
+        let count_first_step = |neighborhood: Neighborhood, max_occupied_adjacent: usize| {
+            let rules = AccessibilityRules::new(&neighborhood, max_occupied_adjacent, EdgeMode::Empty);
+            count_accessible_rolls(&mut parse_paper_rolls(rack, RackSymbols::default()).unwrap(), &rules, false)
+        };
+
+        // Corners have 2 edge neighbors, sides 3 and the center 4.
+        assert_eq!(count_first_step(Neighborhood::VonNeumann, 2), 4);
+        assert_eq!(count_first_step(Neighborhood::VonNeumann, 3), 8);
+
+        // With radius 2, every roll sees the whole 3×3 block.
+        assert_eq!(count_first_step(Neighborhood::Moore { radius: 2 }, 7), 0);
+        assert_eq!(count_first_step(Neighborhood::Moore { radius: 2 }, 8), 9);
+
+        // Hex corners: top left and bottom right have 2 neighbors, the other two have 3.
+        assert_eq!(count_first_step(Neighborhood::Hexagonal, 2), 2);
+    }
+
+    #[test]
+    fn test_edge_modes() {
+        let count_first_step = |rack: &str, max_occupied_adjacent: usize, edge_mode: EdgeMode| {
+            let rules = AccessibilityRules::new(&Neighborhood::default(), max_occupied_adjacent, edge_mode);
+            count_accessible_rolls(&mut parse_paper_rolls(rack, RackSymbols::default()).unwrap(), &rules, false)
+        };
+
+        // Walls add 5 occupied neighbors to the corners and 3 to the sides.
+        assert_eq!(count_first_step("@@@\n@@@\n@@@", 3, EdgeMode::Empty), 4);
+        assert_eq!(count_first_step("@@@\n@@@\n@@@", 6, EdgeMode::Wall), 0);
+        assert_eq!(count_first_step("@@@\n@@@\n@@@", 8, EdgeMode::Wall), 9);
+
+        // Opposite corners touch each other once the rack wraps around.
+        assert_eq!(count_first_step("@...\n....\n....\n...@", 0, EdgeMode::Empty), 2);
+        assert_eq!(count_first_step("@...\n....\n....\n...@", 0, EdgeMode::Toroidal), 0);
+    }
+
+    #[test]
+    fn test_edge_modes_worklist() {
+        let rack = parse_paper_rolls("@@@@\n@@@@\n@@@@", RackSymbols::default()).unwrap();
+
+        for edge_mode in [EdgeMode::Empty, EdgeMode::Wall, EdgeMode::Toroidal] {
+            let rules = AccessibilityRules::new(&Neighborhood::default(), 4, edge_mode);
+            let removal_depths = find_removal_depths(&rack, &rules);
+
+            assert_eq!(
+                removal_histogram(&removal_depths),
+                removal_histogram_by_rounds(&rack, &rules),
+                "{edge_mode:?}"
+            );
+        }
+    }
+
+    fn removal_histogram_by_rounds(rack: &PaperRollRack, rules: &AccessibilityRules) -> Vec<usize> {
+        let mut rack = rack.clone();
+
+        (0..)
+            .map(|_| remove_accessible_rolls(&mut rack, rules, RemovalMode::Synchronous))
+            .take_while(|removed_rolls| *removed_rolls > 0)
+            .collect_vec()
+    }
+
+    #[test]
+    fn test_asymmetric_neighborhood_worklist() {
+        // Every roll only looks at the cell to its right, so rolls are removed right to left.
+        let rules = AccessibilityRules::new(&Neighborhood::Custom(vec![(0, 1)]), 0, EdgeMode::Empty);
+        let rack = parse_paper_rolls("@@@@.@@", RackSymbols::default()).unwrap();
+        let removal_depths = find_removal_depths(&rack, &rules);
+
+        assert_eq!(render_depth_map(&rack, &removal_depths), "4321.21");
+
+        let mut swept_rack = rack.clone();
+        assert_eq!(
+            run_removal_rounds(&mut swept_rack, &rules, RemovalMode::Synchronous),
+            RemovalReport { removed_rolls: 6, rounds: 4 }
+        );
+    }
 }
@@ -0,0 +1,180 @@
+use itertools::Itertools;
+use std::str::FromStr;
+
+/// Cells that count as neighbors of a rack cell, as `(row, column)` offsets.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Neighborhood {
+    /// The four cells sharing an edge with the cell.
+    VonNeumann,
+    /// All cells within `radius` steps in both directions (radius 1 is the usual 3×3 block).
+    Moore { radius: usize },
+    /// The six neighbors of a hex grid stored in axial coordinates: every row is shifted half a cell to the right of
+    /// the one above it, so a cell touches columns `c` and `c + 1` above it and columns `c - 1` and `c` below it.
+    Hexagonal,
+    /// An explicit list of offsets. The cell itself (`(0, 0)`) is never counted.
+    Custom(Vec<(isize, isize)>),
+}
+
+/// What the cells beyond the rack edges look like to the neighborhood of a roll.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum EdgeMode {
+    /// Everything outside the rack is empty.
+    #[default]
+    Empty,
+    /// The rack is surrounded by solid walls, which count as occupied neighbors.
+    Wall,
+    /// The rack wraps around, so leaving it on one edge enters it again on the opposite one.
+    Toroidal,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidNeighborhood;
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidEdgeMode;
+
+impl Default for Neighborhood {
+    fn default() -> Self {
+        Neighborhood::Moore { radius: 1 }
+    }
+}
+
+impl Neighborhood {
+    pub fn offsets(&self) -> Vec<(isize, isize)> {
+        match self {
+            Neighborhood::VonNeumann => vec![(-1, 0), (0, -1), (0, 1), (1, 0)],
+            Neighborhood::Moore { radius } => {
+                let radius = *radius as isize;
+
+                (-radius..=radius)
+                    .cartesian_product(-radius..=radius)
+                    .filter(|offset| *offset != (0, 0))
+                    .collect_vec()
+            }
+            Neighborhood::Hexagonal => vec![(-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0)],
+            Neighborhood::Custom(offsets) => offsets.iter().copied().filter(|offset| *offset != (0, 0)).collect_vec(),
+        }
+    }
+}
+
+/// Parses `von-neumann`, `moore`, `moore:<radius>`, `hex` or `custom:<row>,<column>;<row>,<column>;...`.
+impl FromStr for Neighborhood {
+    type Err = InvalidNeighborhood;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once(':') {
+            None => match value {
+                "von-neumann" => Ok(Neighborhood::VonNeumann),
+                "moore" => Ok(Neighborhood::default()),
+                "hex" => Ok(Neighborhood::Hexagonal),
+                _ => Err(InvalidNeighborhood),
+            },
+            Some(("moore", radius)) => {
+                let radius = radius.parse().map_err(|_| InvalidNeighborhood)?;
+                Ok(Neighborhood::Moore { radius })
+            }
+            Some(("custom", offsets)) => offsets
+                .split(';')
+                .map(parse_offset)
+                .collect::<Result<Vec<_>, _>>()
+                .map(Neighborhood::Custom),
+            Some(_) => Err(InvalidNeighborhood),
+        }
+    }
+}
+
+/// Parses `empty`, `wall` or `torus`.
+impl FromStr for EdgeMode {
+    type Err = InvalidEdgeMode;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "empty" => Ok(EdgeMode::Empty),
+            "wall" => Ok(EdgeMode::Wall),
+            "torus" => Ok(EdgeMode::Toroidal),
+            _ => Err(InvalidEdgeMode),
+        }
+    }
+}
+
+/// Moves from a cell of a `height` × `width` rack by the given offset. Returns `None` when that leaves the rack, which
+/// can only happen for edge modes that don't wrap around.
+pub fn neighbor_cell(
+    (height, width): (usize, usize),
+    (row, column): (usize, usize),
+    (row_offset, column_offset): (isize, isize),
+    edge_mode: EdgeMode,
+) -> Option<(usize, usize)> {
+    if edge_mode == EdgeMode::Toroidal {
+        let neighbor_row = (row as isize + row_offset).rem_euclid(height as isize);
+        let neighbor_column = (column as isize + column_offset).rem_euclid(width as isize);
+
+        return Some((neighbor_row as usize, neighbor_column as usize));
+    }
+
+    let neighbor_row = row.checked_add_signed(row_offset).filter(|row| *row < height)?;
+    let neighbor_column = column.checked_add_signed(column_offset).filter(|column| *column < width)?;
+
+    Some((neighbor_row, neighbor_column))
+}
+
+fn parse_offset(offset: &str) -> Result<(isize, isize), InvalidNeighborhood> {
+    let (row, column) = offset.split_once(',').ok_or(InvalidNeighborhood)?;
+
+    match (row.trim().parse(), column.trim().parse()) {
+        (Ok(row), Ok(column)) => Ok((row, column)),
+        _ => Err(InvalidNeighborhood),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offsets() {
+        assert_eq!(Neighborhood::VonNeumann.offsets().len(), 4);
+        assert_eq!(Neighborhood::Moore { radius: 1 }.offsets().len(), 8);
+        assert_eq!(Neighborhood::Moore { radius: 2 }.offsets().len(), 24);
+        assert_eq!(Neighborhood::Hexagonal.offsets().len(), 6);
+        assert_eq!(Neighborhood::Custom(vec![(0, 0), (0, 2)]).offsets(), vec![(0, 2)]);
+    }
+
+    #[test]
+    fn test_parse_neighborhood() {
+        assert_eq!("von-neumann".parse(), Ok(Neighborhood::VonNeumann));
+        assert_eq!("moore".parse(), Ok(Neighborhood::Moore { radius: 1 }));
+        assert_eq!("moore:3".parse(), Ok(Neighborhood::Moore { radius: 3 }));
+        assert_eq!("hex".parse(), Ok(Neighborhood::Hexagonal));
+        assert_eq!(
+            "custom:-1,0;2, 1".parse(),
+            Ok(Neighborhood::Custom(vec![(-1, 0), (2, 1)]))
+        );
+    }
+
+    #[test]
+    fn test_neighbor_cell() {
+        assert_eq!(neighbor_cell((3, 4), (1, 1), (-1, 2), EdgeMode::Empty), Some((0, 3)));
+        assert_eq!(neighbor_cell((3, 4), (0, 0), (-1, 0), EdgeMode::Empty), None);
+        assert_eq!(neighbor_cell((3, 4), (2, 3), (0, 1), EdgeMode::Wall), None);
+
+        assert_eq!(neighbor_cell((3, 4), (0, 0), (-1, -1), EdgeMode::Toroidal), Some((2, 3)));
+        assert_eq!(neighbor_cell((3, 4), (2, 3), (2, 6), EdgeMode::Toroidal), Some((1, 1)));
+    }
+
+    #[test]
+    fn test_parse_edge_mode() {
+        assert_eq!("empty".parse(), Ok(EdgeMode::Empty));
+        assert_eq!("wall".parse(), Ok(EdgeMode::Wall));
+        assert_eq!("torus".parse(), Ok(EdgeMode::Toroidal));
+        assert_eq!("mirror".parse::<EdgeMode>(), Err(InvalidEdgeMode));
+    }
+
+    #[test]
+    fn test_parse_neighborhood_invalid() {
+        assert_eq!("square".parse::<Neighborhood>(), Err(InvalidNeighborhood));
+        assert_eq!("moore:x".parse::<Neighborhood>(), Err(InvalidNeighborhood));
+        assert_eq!("custom:1".parse::<Neighborhood>(), Err(InvalidNeighborhood));
+        assert_eq!("custom:1,a".parse::<Neighborhood>(), Err(InvalidNeighborhood));
+    }
+}
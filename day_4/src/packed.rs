@@ -0,0 +1,356 @@
+use crate::neighborhood::EdgeMode;
+use crate::{AccessibilityRules, RemovalReport};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Rack stored as one bit per cell. Every row starts on a fresh 64-bit word (column `c` is bit `c % 64` of word
+/// `c / 64`), and the bits past the rack width are always zero.
+///
+/// Accessibility is computed a whole row at a time: every neighborhood offset turns into a shifted copy of another
+/// row, and those copies are summed into bit-sliced counters (one word per bit of the count), so each word operation
+/// handles 64 cells at once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackedRack {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    bits: Vec<u64>,
+}
+
+impl PackedRack {
+    /// Creates a rack of the given width without any rows yet.
+    pub fn with_width(width: usize) -> Self {
+        PackedRack {
+            width,
+            height: 0,
+            words_per_row: width.div_ceil(WORD_BITS),
+            bits: Vec::new(),
+        }
+    }
+
+    /// Appends a row to the bottom of the rack. Cells past the rack width are ignored.
+    pub fn push_row(&mut self, cells: &[bool]) {
+        let row_start = self.bits.len();
+        self.bits.resize(row_start + self.words_per_row, 0);
+
+        for (column, _) in cells.iter().take(self.width).enumerate().filter(|(_, occupied)| **occupied) {
+            self.bits[row_start + column / WORD_BITS] |= 1 << (column % WORD_BITS);
+        }
+
+        self.height += 1;
+    }
+
+    pub fn count_rolls(&self) -> usize {
+        self.bits.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn count_accessible_rolls(&self, rules: &AccessibilityRules) -> usize {
+        self.accessible_mask(rules).iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Removes all rolls that are accessible at the start of the round (synchronous removal), returning how many.
+    pub fn remove_accessible_rolls(&mut self, rules: &AccessibilityRules) -> usize {
+        let accessible_mask = self.accessible_mask(rules);
+
+        self.bits
+            .iter_mut()
+            .zip(&accessible_mask)
+            .for_each(|(word, accessible)| *word &= !accessible);
+
+        accessible_mask.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn run_removal_rounds(&mut self, rules: &AccessibilityRules) -> RemovalReport {
+        let mut report = RemovalReport { removed_rolls: 0, rounds: 0 };
+
+        loop {
+            let extracted_rolls = self.remove_accessible_rolls(rules);
+            if extracted_rolls == 0 {
+                break;
+            }
+
+            report.removed_rolls += extracted_rolls;
+            report.rounds += 1;
+        }
+
+        report
+    }
+
+    /// Bits of all rolls that are accessible right now, laid out like the rack.
+    fn accessible_mask(&self, rules: &AccessibilityRules) -> Vec<u64> {
+        let plane_count = (usize::BITS - rules.offsets.len().leading_zeros()) as usize;
+        let mut accessible_mask = vec![0; self.bits.len()];
+        let mut count_planes = vec![vec![0u64; self.words_per_row]; plane_count];
+        let mut scratch = RowScratch::new(self);
+
+        for row in 0..self.height {
+            count_planes.iter_mut().for_each(|plane| plane.fill(0));
+
+            for offset in &rules.offsets {
+                self.shifted_row(row, *offset, rules.edge_mode, &mut scratch);
+                add_to_counter(&mut count_planes, &scratch.neighbors);
+            }
+
+            for (index, word) in self.row(row).iter().enumerate() {
+                accessible_mask[row * self.words_per_row + index] =
+                    word & at_most(&count_planes, rules.max_occupied_adjacent, index);
+            }
+        }
+
+        accessible_mask
+    }
+
+    fn row(&self, row: usize) -> &[u64] {
+        &self.bits[row * self.words_per_row..(row + 1) * self.words_per_row]
+    }
+
+    /// Fills `scratch.neighbors` with the row whose bit `c` says whether the cell at `(row, c) + offset` is occupied,
+    /// including the cells beyond the rack edges as defined by the edge mode.
+    fn shifted_row(
+        &self,
+        row: usize,
+        (row_offset, column_offset): (isize, isize),
+        edge_mode: EdgeMode,
+        scratch: &mut RowScratch,
+    ) {
+        let source_row = row as isize + row_offset;
+
+        let source = if (0..self.height as isize).contains(&source_row) {
+            self.row(source_row as usize)
+        } else {
+            match edge_mode {
+                EdgeMode::Empty => &scratch.empty_row,
+                EdgeMode::Wall => &scratch.full_row,
+                EdgeMode::Toroidal => self.row(source_row.rem_euclid(self.height as isize) as usize),
+            }
+        };
+
+        match edge_mode {
+            EdgeMode::Empty => self.shift_columns(source, column_offset, &mut scratch.neighbors),
+            EdgeMode::Wall => {
+                self.shift_columns(source, column_offset, &mut scratch.neighbors);
+                self.shift_columns(&scratch.full_row, column_offset, &mut scratch.spare);
+
+                let outside = scratch.full_row.iter().zip(&scratch.spare).map(|(full, inside)| full & !inside);
+                for (neighbor, outside) in scratch.neighbors.iter_mut().zip(outside) {
+                    *neighbor |= outside;
+                }
+            }
+            EdgeMode::Toroidal => {
+                let wrapped_offset = column_offset.rem_euclid(self.width as isize);
+                self.shift_columns(source, wrapped_offset, &mut scratch.neighbors);
+                self.shift_columns(source, wrapped_offset - self.width as isize, &mut scratch.spare);
+
+                for (head, tail) in scratch.neighbors.iter_mut().zip(&scratch.spare) {
+                    *head |= tail;
+                }
+            }
+        }
+    }
+
+    /// Moves the cells of a row into `shifted` so that bit `c` of the result is bit `c + offset` of the input, with
+    /// zeroes for columns outside the rack.
+    fn shift_columns(&self, words: &[u64], offset: isize, shifted: &mut [u64]) {
+        let word_shift = offset.unsigned_abs() / WORD_BITS;
+        let bit_shift = offset.unsigned_abs() % WORD_BITS;
+        let word_at = |index: Option<usize>| index.and_then(|index| words.get(index)).copied().unwrap_or(0);
+
+        for (index, word) in shifted.iter_mut().enumerate() {
+            *word = if offset >= 0 {
+                let low = word_at(index.checked_add(word_shift));
+                let high = word_at(index.checked_add(word_shift + 1));
+
+                if bit_shift == 0 { low } else { (low >> bit_shift) | (high << (WORD_BITS - bit_shift)) }
+            } else {
+                let high = word_at(index.checked_sub(word_shift));
+                let low = word_at(index.checked_sub(word_shift + 1));
+
+                if bit_shift == 0 { high } else { (high << bit_shift) | (low >> (WORD_BITS - bit_shift)) }
+            };
+        }
+
+        if let Some(last) = shifted.last_mut() {
+            *last &= self.last_word_mask();
+        }
+    }
+
+    fn full_row_mask(&self) -> Vec<u64> {
+        let mut mask = vec![u64::MAX; self.words_per_row];
+        if let Some(last) = mask.last_mut() {
+            *last = self.last_word_mask();
+        }
+
+        mask
+    }
+
+    fn last_word_mask(&self) -> u64 {
+        match self.width % WORD_BITS {
+            0 => u64::MAX,
+            used_bits => (1 << used_bits) - 1,
+        }
+    }
+}
+
+/// Row buffers that `accessible_mask` reuses for every row and offset, so the shifts don't allocate.
+struct RowScratch {
+    empty_row: Vec<u64>,
+    full_row: Vec<u64>,
+    neighbors: Vec<u64>,
+    spare: Vec<u64>,
+}
+
+impl RowScratch {
+    fn new(rack: &PackedRack) -> Self {
+        RowScratch {
+            empty_row: vec![0; rack.words_per_row],
+            full_row: rack.full_row_mask(),
+            neighbors: vec![0; rack.words_per_row],
+            spare: vec![0; rack.words_per_row],
+        }
+    }
+}
+
+/// Adds one to every bit-sliced counter whose bit is set in `mask` (a ripple-carry add across the planes).
+fn add_to_counter(count_planes: &mut [Vec<u64>], mask: &[u64]) {
+    for (index, word) in mask.iter().enumerate() {
+        let mut carry = *word;
+
+        for plane in count_planes.iter_mut() {
+            if carry == 0 {
+                break;
+            }
+
+            let next_carry = plane[index] & carry;
+            plane[index] ^= carry;
+            carry = next_carry;
+        }
+    }
+}
+
+/// Bits of the counters in word `index` that hold at most `limit`, comparing the planes from the most significant one
+/// down.
+fn at_most(count_planes: &[Vec<u64>], limit: usize, index: usize) -> u64 {
+    if count_planes.len() < usize::BITS as usize && limit >> count_planes.len() != 0 {
+        return u64::MAX;
+    }
+
+    let mut below = 0;
+    let mut equal = u64::MAX;
+
+    for (bit, plane) in count_planes.iter().enumerate().rev() {
+        if limit >> bit & 1 == 1 {
+            below |= equal & !plane[index];
+            equal &= plane[index];
+        } else {
+            equal &= !plane[index];
+        }
+    }
+
+    below | equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neighborhood::Neighborhood;
+    use crate::tests::next_random;
+    use crate::{PaperRollRack, RemovalMode, count_accessible_rolls, run_removal_rounds};
+
+    fn pack_rack(rack: &PaperRollRack) -> PackedRack {
+        let mut packed = PackedRack::with_width(rack[0].len());
+        rack.iter().for_each(|cells| packed.push_row(cells));
+
+        packed
+    }
+
+    fn random_rack(height: usize, width: usize, seed: u64) -> PaperRollRack {
+        let mut state = seed;
+
+        (0..height)
+            .map(|_| {
+                (0..width)
+                    .map(|_| !(next_random(&mut state) >> 33).is_multiple_of(3))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_push_row() {
+        let mut packed = PackedRack::with_width(3);
+        packed.push_row(&[true, false, true]);
+        packed.push_row(&[false, false, true, true]);
+
+        assert_eq!(packed.height, 2);
+        assert_eq!(packed.bits, vec![0b101, 0b100]);
+        assert_eq!(packed.count_rolls(), 3);
+    }
+
+    #[test]
+    fn test_shift_columns_across_words() {
+        let mut rack = vec![vec![false; 130]];
+        rack[0][0] = true;
+        rack[0][64] = true;
+        rack[0][129] = true;
+        let packed = pack_rack(&rack);
+
+        let mut shifted = vec![0; 3];
+
+        packed.shift_columns(packed.row(0), 1, &mut shifted);
+        assert_eq!(shifted, vec![1 << 63, 0, 1]);
+        packed.shift_columns(packed.row(0), -1, &mut shifted);
+        assert_eq!(shifted, vec![0b10, 0b10, 0]);
+        packed.shift_columns(packed.row(0), 65, &mut shifted);
+        assert_eq!(shifted, vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn test_matches_dense_rack() {
+        let neighborhoods = [
+            Neighborhood::default(),
+            Neighborhood::VonNeumann,
+            Neighborhood::Moore { radius: 2 },
+            Neighborhood::Hexagonal,
+            Neighborhood::Custom(vec![(0, 70), (-1, -65)]),
+        ];
+
+        for (index, (height, width)) in [(1, 1), (5, 7), (9, 64), (12, 130)].into_iter().enumerate() {
+            let rack = random_rack(height, width, index as u64);
+
+            for neighborhood in &neighborhoods {
+                for edge_mode in [EdgeMode::Empty, EdgeMode::Wall, EdgeMode::Toroidal] {
+                    let rules = AccessibilityRules::new(neighborhood, 3, edge_mode);
+                    let mut packed = pack_rack(&rack);
+
+                    assert_eq!(
+                        packed.count_accessible_rolls(&rules),
+                        count_accessible_rolls(&mut rack.clone(), &rules, false),
+                        "{height}×{width} {neighborhood:?} {edge_mode:?}"
+                    );
+                    assert_eq!(
+                        packed.run_removal_rounds(&rules),
+                        run_removal_rounds(&mut rack.clone(), &rules, RemovalMode::Synchronous),
+                        "{height}×{width} {neighborhood:?} {edge_mode:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let rack = random_rack(20, 70, 42);
+        let packed = pack_rack(&rack);
+
+        for max_occupied_adjacent in 0..=30 {
+            let neighborhood = Neighborhood::Moore { radius: 2 };
+            let rules = AccessibilityRules::new(&neighborhood, max_occupied_adjacent, EdgeMode::Wall);
+
+            assert_eq!(
+                packed.count_accessible_rolls(&rules),
+                count_accessible_rolls(&mut rack.clone(), &rules, false),
+                "{max_occupied_adjacent}"
+            );
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{AccessibilityRules, PaperRollRack, find_accessible_rolls};
+use itertools::Itertools;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// What a single rack cell looks like in a rendered frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellState {
+    Empty,
+    Roll,
+    /// A roll that can be removed in the current round.
+    Accessible,
+}
+
+/// Binary netpbm image formats used for exported frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ImageFormat {
+    /// Color image (`P6`), accessible rolls drawn in red.
+    #[default]
+    Ppm,
+    /// Grayscale image (`P5`), accessible rolls drawn in gray.
+    Pgm,
+}
+
+impl ImageFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Ppm => "ppm",
+            ImageFormat::Pgm => "pgm",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "ppm" => Ok(ImageFormat::Ppm),
+            "pgm" => Ok(ImageFormat::Pgm),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Where and how the frames of the removal process are written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameExport {
+    pub directory: String,
+    pub format: ImageFormat,
+    /// Side length in pixels of a single rack cell.
+    pub scale: usize,
+}
+
+/// Replays the synchronous removal rounds, drawing the rack at the start of every round (and once more after the last
+/// one) as text and/or as numbered image frames.
+pub fn render_removal_rounds(
+    rack: &PaperRollRack,
+    rules: &AccessibilityRules,
+    print_ascii: bool,
+    frame_export: Option<&FrameExport>,
+) -> io::Result<()> {
+    let mut remaining_rack = rack.clone();
+
+    if let Some(frame_export) = frame_export {
+        fs::create_dir_all(&frame_export.directory)?;
+    }
+
+    for round in 1.. {
+        let accessible_rolls = find_accessible_rolls(&remaining_rack, rules);
+        let cells = classify_cells(&remaining_rack, &accessible_rolls);
+
+        if print_ascii {
+            if accessible_rolls.is_empty() {
+                println!("Final rack:");
+            } else {
+                println!("Round {round} ({} accessible):", accessible_rolls.len());
+            }
+            println!("{}", render_ascii(&cells));
+            println!();
+        }
+
+        if let Some(frame_export) = frame_export {
+            let file_name = format!("frame_{:04}.{}", round - 1, frame_export.format.extension());
+            let frame = encode_frame(&cells, frame_export.format, frame_export.scale);
+            fs::write(Path::new(&frame_export.directory).join(file_name), frame)?;
+        }
+
+        if accessible_rolls.is_empty() {
+            break;
+        }
+
+        for (row, column) in accessible_rolls {
+            remaining_rack[row][column] = false;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn classify_cells(rack: &PaperRollRack, accessible_rolls: &[(usize, usize)]) -> Vec<Vec<CellState>> {
+    let mut cells = rack
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|occupied| if *occupied { CellState::Roll } else { CellState::Empty })
+                .collect_vec()
+        })
+        .collect_vec();
+
+    for (row, column) in accessible_rolls {
+        cells[*row][*column] = CellState::Accessible;
+    }
+
+    cells
+}
+
+/// Draws the cells with `@` for rolls, `x` for accessible rolls and `.` for empty cells.
+pub fn render_ascii(cells: &[Vec<CellState>]) -> String {
+    cells
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| match cell {
+                    CellState::Empty => '.',
+                    CellState::Roll => '@',
+                    CellState::Accessible => 'x',
+                })
+                .collect::<String>()
+        })
+        .join("\n")
+}
+
+/// Encodes the cells as a binary netpbm image in which every cell is a `scale` × `scale` block of pixels.
+pub fn encode_frame(cells: &[Vec<CellState>], format: ImageFormat, scale: usize) -> Vec<u8> {
+    let height = cells.len();
+    let width = cells.first().map_or(0, |row| row.len());
+
+    let magic_number = match format {
+        ImageFormat::Ppm => "P6",
+        ImageFormat::Pgm => "P5",
+    };
+    let mut image = format!("{magic_number}\n{} {}\n255\n", width * scale, height * scale).into_bytes();
+
+    for row in cells {
+        let pixel_row = row
+            .iter()
+            .flat_map(|cell| pixel(*cell, format).repeat(scale))
+            .collect_vec();
+
+        for _ in 0..scale {
+            image.extend_from_slice(&pixel_row);
+        }
+    }
+
+    image
+}
+
+fn pixel(cell: CellState, format: ImageFormat) -> Vec<u8> {
+    match (format, cell) {
+        (ImageFormat::Ppm, CellState::Empty) => vec![245, 245, 240],
+        (ImageFormat::Ppm, CellState::Roll) => vec![60, 60, 60],
+        (ImageFormat::Ppm, CellState::Accessible) => vec![220, 40, 40],
+        (ImageFormat::Pgm, CellState::Empty) => vec![255],
+        (ImageFormat::Pgm, CellState::Roll) => vec![0],
+        (ImageFormat::Pgm, CellState::Accessible) => vec![128],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neighborhood::{EdgeMode, Neighborhood};
+    use crate::tests::EXAMPLE_INPUT;
+    use crate::{RackSymbols, parse_paper_rolls};
+
+    #[test]
+    fn test_render_ascii_example_data() {
+        let rack = parse_paper_rolls(EXAMPLE_INPUT, RackSymbols::default()).unwrap();
+        let rules = AccessibilityRules::new(&Neighborhood::default(), 3, EdgeMode::Empty);
+        let cells = classify_cells(&rack, &find_accessible_rolls(&rack, &rules));
+
+        assert_eq!(
+            render_ascii(&cells),
+            "..xx.xx@x.\nx@@.@.@.@@\n@@@@@.x.@@\n@.@@@@..@.\nx@.@@@@.@x\n.@@@@@@@.@\n.@.@.@.@@@\nx.@@@.@@@@\n.@@@@@@@@.\nx.x.@@@.x."
+        );
+    }
+
+    #[test]
+    fn test_encode_frame() {
+        let cells = vec![vec![CellState::Empty, CellState::Accessible], vec![CellState::Roll, CellState::Empty]];
+
+        let mut expected = b"P5\n4 4\n255\n".to_vec();
+        expected.extend([255, 255, 128, 128].repeat(2));
+        expected.extend([0, 0, 255, 255].repeat(2));
+        assert_eq!(encode_frame(&cells, ImageFormat::Pgm, 2), expected);
+
+        let mut expected = b"P6\n2 2\n255\n".to_vec();
+        expected.extend([245, 245, 240, 220, 40, 40, 60, 60, 60, 245, 245, 240]);
+        assert_eq!(encode_frame(&cells, ImageFormat::Ppm, 1), expected);
+    }
+}
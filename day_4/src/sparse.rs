@@ -0,0 +1,182 @@
+use crate::{AccessibilityRules, RackSymbols, RemovalMode, RemovalReport};
+use itertools::Itertools;
+use std::collections::HashSet;
+
+/// Racks filled below this fraction of their cells are stored sparsely when the representation is picked
+/// automatically.
+pub const SPARSE_DENSITY_THRESHOLD: f64 = 0.05;
+
+/// Rack stored as the set of coordinates holding a roll. Coordinates are unbounded (and may be negative), and
+/// everything that isn't in the set is empty, so there are no rack edges to take care of.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseRack {
+    rolls: HashSet<(i64, i64)>,
+}
+
+impl SparseRack {
+    pub fn count_accessible_rolls(&self, rules: &AccessibilityRules) -> usize {
+        self.rolls.iter().filter(|roll| self.is_accessible(**roll, rules)).count()
+    }
+
+    /// Removes accessible rolls round by round until a round finds none, with the same rounds as a dense rack.
+    pub fn run_removal_rounds(&mut self, rules: &AccessibilityRules, mode: RemovalMode) -> RemovalReport {
+        match mode {
+            RemovalMode::Synchronous => self.run_synchronous_removal_rounds(rules),
+            RemovalMode::InPlace => self.run_in_place_removal_rounds(rules),
+        }
+    }
+
+    /// After the first round only the remaining neighbors of removed rolls can have become accessible, so only those
+    /// are checked again.
+    fn run_synchronous_removal_rounds(&mut self, rules: &AccessibilityRules) -> RemovalReport {
+        let mut report = RemovalReport { removed_rolls: 0, rounds: 0 };
+        let mut candidates = self.rolls.iter().copied().collect_vec();
+
+        loop {
+            let accessible_rolls = candidates
+                .into_iter()
+                .filter(|roll| self.is_accessible(*roll, rules))
+                .collect_vec();
+            if accessible_rolls.is_empty() {
+                break;
+            }
+
+            for roll in &accessible_rolls {
+                self.rolls.remove(roll);
+            }
+
+            report.removed_rolls += accessible_rolls.len();
+            report.rounds += 1;
+
+            // A removed roll was a neighbor of the cells at the negated offsets.
+            candidates = accessible_rolls
+                .iter()
+                .flat_map(|(row, column)| {
+                    rules.offsets.iter().map(move |(row_offset, column_offset)| {
+                        (row - *row_offset as i64, column - *column_offset as i64)
+                    })
+                })
+                .filter(|cell| self.rolls.contains(cell))
+                .unique()
+                .collect_vec();
+        }
+
+        report
+    }
+
+    /// Visits the rolls in the scan order of a dense rack (row by row, left to right) and removes every accessible
+    /// one right away, so later rolls already see the earlier removals.
+    fn run_in_place_removal_rounds(&mut self, rules: &AccessibilityRules) -> RemovalReport {
+        let mut report = RemovalReport { removed_rolls: 0, rounds: 0 };
+
+        loop {
+            let mut removed_rolls = 0;
+
+            for roll in self.rolls.iter().copied().sorted_unstable().collect_vec() {
+                if self.is_accessible(roll, rules) {
+                    self.rolls.remove(&roll);
+                    removed_rolls += 1;
+                }
+            }
+
+            if removed_rolls == 0 {
+                break;
+            }
+
+            report.removed_rolls += removed_rolls;
+            report.rounds += 1;
+        }
+
+        report
+    }
+
+    fn is_accessible(&self, (row, column): (i64, i64), rules: &AccessibilityRules) -> bool {
+        let occupied_neighbor_count = rules
+            .offsets
+            .iter()
+            .filter(|(row_offset, column_offset)| {
+                self.rolls.contains(&(row + *row_offset as i64, column + *column_offset as i64))
+            })
+            .count();
+
+        occupied_neighbor_count <= rules.max_occupied_adjacent
+    }
+}
+
+impl FromIterator<(i64, i64)> for SparseRack {
+    fn from_iter<T: IntoIterator<Item = (i64, i64)>>(iter: T) -> Self {
+        SparseRack {
+            rolls: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Fraction of the rack cells that hold a roll, counted on the input text so that the rack doesn't have to be parsed
+/// (into either representation) first.
+pub fn fill_density(data: &str, symbols: RackSymbols) -> f64 {
+    let roll_count = data.chars().filter(|symbol| *symbol == symbols.occupied).count();
+    let cell_count = roll_count + data.chars().filter(|symbol| *symbol == symbols.empty).count();
+
+    roll_count as f64 / cell_count.max(1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neighborhood::{EdgeMode, Neighborhood};
+    use crate::tests::EXAMPLE_INPUT;
+    use crate::{count_accessible_rolls, parse_paper_rolls, parse_sparse_paper_rolls, run_removal_rounds};
+
+    #[test]
+    fn test_matches_dense_rack() {
+        let rack = parse_paper_rolls(EXAMPLE_INPUT, RackSymbols::default()).unwrap();
+        let sparse = parse_sparse_paper_rolls(EXAMPLE_INPUT, RackSymbols::default()).unwrap();
+
+        for neighborhood in [Neighborhood::default(), Neighborhood::VonNeumann, Neighborhood::Hexagonal] {
+            for max_occupied_adjacent in [1, 2, 3] {
+                let rules = AccessibilityRules::new(&neighborhood, max_occupied_adjacent, EdgeMode::Empty);
+
+                assert_eq!(
+                    sparse.count_accessible_rolls(&rules),
+                    count_accessible_rolls(&mut rack.clone(), &rules, false)
+                );
+
+                for mode in [RemovalMode::Synchronous, RemovalMode::InPlace] {
+                    assert_eq!(
+                        sparse.clone().run_removal_rounds(&rules, mode),
+                        run_removal_rounds(&mut rack.clone(), &rules, mode)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_unbounded_coordinates() {
+        let rules = AccessibilityRules::new(&Neighborhood::default(), 3, EdgeMode::Empty);
+        let far = 1 << 40;
+
+        // A solid 3×3 block far out in negative space plus a lone roll on the other side.
+        let mut sparse = (-far..-far + 3)
+            .cartesian_product(-far..-far + 3)
+            .chain([(far, far)])
+            .collect::<SparseRack>();
+
+        assert_eq!(sparse.count_accessible_rolls(&rules), 5);
+        assert_eq!(
+            sparse.clone().run_removal_rounds(&rules, RemovalMode::InPlace),
+            RemovalReport { removed_rolls: 10, rounds: 2 }
+        );
+        assert_eq!(
+            sparse.run_removal_rounds(&rules, RemovalMode::Synchronous),
+            RemovalReport { removed_rolls: 10, rounds: 3 }
+        );
+        assert!(sparse.rolls.is_empty());
+    }
+
+    #[test]
+    fn test_fill_density() {
+        assert_eq!(fill_density("@.\n..", RackSymbols::default()), 0.25);
+        assert_eq!(fill_density("", RackSymbols::default()), 0.0);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{combine_overlapping_ranges, count_ids, ranges_overlap};
+use itertools::Itertools;
+use std::cmp::{max, min};
+use std::str::FromStr;
+
+/// Set operations between two range lists. All of them take and return sorted, disjoint ranges (as returned by
+/// `combine_overlapping_ranges` with adjacent ranges merged).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SetOperation {
+    Union,
+    Intersection,
+    /// IDs in the first list but not in the second one.
+    Difference,
+    SymmetricDifference,
+}
+
+impl SetOperation {
+    pub const ALL: [SetOperation; 4] = [
+        SetOperation::Union,
+        SetOperation::Intersection,
+        SetOperation::Difference,
+        SetOperation::SymmetricDifference,
+    ];
+
+    pub fn apply(&self, a: &[(u64, u64)], b: &[(u64, u64)]) -> Vec<(u64, u64)> {
+        match self {
+            SetOperation::Union => union(a, b),
+            SetOperation::Intersection => intersection(a, b),
+            SetOperation::Difference => difference(a, b),
+            SetOperation::SymmetricDifference => union(&difference(a, b), &difference(b, a)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SetOperation::Union => "union",
+            SetOperation::Intersection => "intersection",
+            SetOperation::Difference => "difference",
+            SetOperation::SymmetricDifference => "symmetric-difference",
+        }
+    }
+}
+
+/// Parses `union`, `intersection`, `difference` or `symmetric-difference`.
+impl FromStr for SetOperation {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        SetOperation::ALL.into_iter().find(|operation| operation.name() == value).ok_or(())
+    }
+}
+
+fn union(a: &[(u64, u64)], b: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    combine_overlapping_ranges(a.iter().chain(b).copied().collect_vec(), true)
+}
+
+fn intersection(a: &[(u64, u64)], b: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let mut result = Vec::new();
+    let (mut a_index, mut b_index) = (0, 0);
+
+    while let (Some(a_range), Some(b_range)) = (a.get(a_index), b.get(b_index)) {
+        if ranges_overlap(a_range, b_range) {
+            result.push((max(a_range.0, b_range.0), min(a_range.1, b_range.1)));
+        }
+
+        // The range that ends first can't overlap anything else in the other list.
+        if a_range.1 < b_range.1 {
+            a_index += 1;
+        } else {
+            b_index += 1;
+        }
+    }
+
+    result
+}
+
+fn difference(a: &[(u64, u64)], b: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let mut result = Vec::new();
+    let mut b_index = 0;
+
+    for (start, end) in a {
+        // Ranges of `b` ending before this range can't cut anything out of the later ones either.
+        while b.get(b_index).is_some_and(|b_range| b_range.1 < *start) {
+            b_index += 1;
+        }
+
+        let mut remaining_start = Some(*start);
+        let mut cut_index = b_index;
+
+        while let (Some(next_start), Some(cut)) = (remaining_start, b.get(cut_index)) {
+            if cut.0 > *end {
+                break;
+            }
+
+            if cut.0 > next_start {
+                result.push((next_start, cut.0 - 1));
+            }
+
+            remaining_start = if cut.1 < *end { Some(cut.1 + 1) } else { None };
+            cut_index += 1;
+        }
+
+        if let Some(remaining_start) = remaining_start {
+            result.push((remaining_start, *end));
+        }
+    }
+
+    result
+}
+
+pub fn render_set_operation(operation: SetOperation, ranges: &[(u64, u64)]) -> String {
+    let header = format!("{}: {} ranges, {} IDs", operation.name(), ranges.len(), count_ids(ranges));
+
+    [header]
+        .into_iter()
+        .chain(ranges.iter().map(|(start, end)| format!("    {start}-{end}")))
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::next_random;
+    use std::collections::BTreeSet;
+
+    fn ids(ranges: &[(u64, u64)]) -> BTreeSet<u64> {
+        ranges.iter().flat_map(|(start, end)| *start..=*end).collect()
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = [(1, 5), (10, 20)];
+        let b = [(3, 12), (15, 15), (18, 25)];
+
+        assert_eq!(SetOperation::Union.apply(&a, &b), vec![(1, 25)]);
+        assert_eq!(SetOperation::Intersection.apply(&a, &b), vec![(3, 5), (10, 12), (15, 15), (18, 20)]);
+        assert_eq!(SetOperation::Difference.apply(&a, &b), vec![(1, 2), (13, 14), (16, 17)]);
+        assert_eq!(
+            SetOperation::SymmetricDifference.apply(&a, &b),
+            vec![(1, 2), (6, 9), (13, 14), (16, 17), (21, 25)]
+        );
+    }
+
+    #[test]
+    fn test_set_operations_match_id_sets() {
+        let mut state = 7u64;
+        let mut random_ranges = || {
+            let ranges = (0..6)
+                .map(|_| {
+                    let random = next_random(&mut state);
+                    let start = (random >> 33) % 60;
+                    (start, start + (random >> 50) % 8)
+                })
+                .collect_vec();
+
+            combine_overlapping_ranges(ranges, true)
+        };
+
+        for _ in 0..200 {
+            let (a, b) = (random_ranges(), random_ranges());
+            let (a_ids, b_ids) = (ids(&a), ids(&b));
+
+            let expected = [
+                a_ids.union(&b_ids).copied().collect::<BTreeSet<_>>(),
+                a_ids.intersection(&b_ids).copied().collect(),
+                a_ids.difference(&b_ids).copied().collect(),
+                a_ids.symmetric_difference(&b_ids).copied().collect(),
+            ];
+
+            for (operation, expected) in SetOperation::ALL.into_iter().zip(expected) {
+                let result = operation.apply(&a, &b);
+
+                assert_eq!(ids(&result), expected, "{operation:?} {a:?} {b:?}");
+                assert_eq!(result, combine_overlapping_ranges(result.clone(), true), "{operation:?} {a:?} {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_difference_at_boundaries() {
+        assert_eq!(difference(&[(0, u64::MAX)], &[(0, 0), (u64::MAX, u64::MAX)]), vec![(1, u64::MAX - 1)]);
+        assert_eq!(difference(&[(0, u64::MAX)], &[(0, u64::MAX)]), vec![]);
+    }
+}
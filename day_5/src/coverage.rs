@@ -0,0 +1,147 @@
+use crate::algebra::SetOperation;
+use crate::{combine_overlapping_ranges, count_ids};
+use itertools::Itertools;
+
+/// What the fresh ranges cover (and don't cover) within a bounding interval.
+#[derive(Debug, PartialEq)]
+pub struct CoverageReport {
+    pub bounds: (u64, u64),
+    /// Maximal runs of IDs within the bounds that no fresh range contains, in order.
+    pub gaps: Vec<(u64, u64)>,
+    pub covered_ids: u128,
+    /// The smallest ID within the bounds contained in the most fresh ranges, with the number of those ranges.
+    pub most_overlapped: Option<(u64, usize)>,
+}
+
+impl CoverageReport {
+    pub fn largest_gap(&self) -> Option<(u64, u64)> {
+        // Ties go to the first gap.
+        self.gaps.iter().copied().rev().max_by_key(|(start, end)| end - start)
+    }
+
+    pub fn coverage(&self) -> f64 {
+        self.covered_ids as f64 / count_ids(&[self.bounds]) as f64
+    }
+}
+
+pub fn analyze_coverage(fresh_ranges: &[(u64, u64)], bounds: (u64, u64)) -> CoverageReport {
+    let merged_ranges = combine_overlapping_ranges(fresh_ranges.to_vec(), true);
+    let clipped_ranges = fresh_ranges
+        .iter()
+        .flat_map(|range| SetOperation::Intersection.apply(&[*range], &[bounds]))
+        .collect_vec();
+
+    CoverageReport {
+        bounds,
+        gaps: SetOperation::Difference.apply(&[bounds], &merged_ranges),
+        covered_ids: count_ids(&SetOperation::Intersection.apply(&[bounds], &merged_ranges)),
+        most_overlapped: find_most_overlapped_id(&clipped_ranges),
+    }
+}
+
+/// Sweeps over the range endpoints in order, keeping track of how many ranges contain the current ID. A range ending
+/// at some ID still contains it, so at equal positions the ranges starting there are counted before the ones ending.
+pub fn find_most_overlapped_id(fresh_ranges: &[(u64, u64)]) -> Option<(u64, usize)> {
+    let events = fresh_ranges
+        .iter()
+        .flat_map(|(start, end)| [(*start, false), (*end, true)])
+        .sorted_unstable();
+
+    let mut most_overlapped: Option<(u64, usize)> = None;
+    let mut overlapping_ranges = 0;
+
+    for (position, is_end) in events {
+        if is_end {
+            overlapping_ranges -= 1;
+            continue;
+        }
+
+        overlapping_ranges += 1;
+        if most_overlapped.is_none_or(|(_, count)| overlapping_ranges > count) {
+            most_overlapped = Some((position, overlapping_ranges));
+        }
+    }
+
+    most_overlapped
+}
+
+pub fn render_coverage_report(report: &CoverageReport) -> String {
+    let (low, high) = report.bounds;
+    let mut lines = vec![format!("Gaps within {low}-{high}: {}", report.gaps.len())];
+
+    lines.extend(report.gaps.iter().map(|(start, end)| format!("    {start}-{end}")));
+
+    if let Some((start, end)) = report.largest_gap() {
+        lines.push(format!("Largest gap: {start}-{end} ({} IDs)", count_ids(&[(start, end)])));
+    }
+
+    lines.push(format!(
+        "Coverage: {:.2}% ({} of {} IDs)",
+        report.coverage() * 100.0,
+        report.covered_ids,
+        count_ids(&[report.bounds])
+    ));
+
+    if let Some((id, count)) = report.most_overlapped {
+        lines.push(format!("Most overlapped ID: {id} ({count} ranges)"));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_coverage() {
+        let report = analyze_coverage(&[(3, 5), (10, 14), (16, 20), (12, 18)], (0, 30));
+
+        assert_eq!(
+            report,
+            CoverageReport {
+                bounds: (0, 30),
+                gaps: vec![(0, 2), (6, 9), (21, 30)],
+                covered_ids: 14,
+                most_overlapped: Some((12, 2)),
+            }
+        );
+        assert_eq!(report.largest_gap(), Some((21, 30)));
+        assert_eq!(report.coverage(), 14.0 / 31.0);
+    }
+
+    #[test]
+    fn test_analyze_coverage_partial_bounds() {
+        let report = analyze_coverage(&[(3, 5), (10, 14)], (4, 11));
+
+        assert_eq!(report.gaps, vec![(6, 9)]);
+        assert_eq!(report.covered_ids, 4);
+    }
+
+    #[test]
+    fn test_analyze_coverage_most_overlapped_within_bounds() {
+        let fresh_ranges = [(3, 5), (4, 8), (5, 9), (20, 30), (25, 40)];
+
+        assert_eq!(analyze_coverage(&fresh_ranges, (0, 50)).most_overlapped, Some((5, 3)));
+        assert_eq!(analyze_coverage(&fresh_ranges, (10, 22)).most_overlapped, Some((20, 1)));
+        assert_eq!(analyze_coverage(&fresh_ranges, (6, 7)).most_overlapped, Some((6, 2)));
+        assert_eq!(analyze_coverage(&fresh_ranges, (10, 19)).most_overlapped, None);
+    }
+
+    #[test]
+    fn test_analyze_coverage_full_width() {
+        let report = analyze_coverage(&[(0, 9), (u64::MAX, u64::MAX)], (0, u64::MAX));
+
+        assert_eq!(report.gaps, vec![(10, u64::MAX - 1)]);
+        assert_eq!(report.covered_ids, 11);
+        assert_eq!(report.largest_gap(), Some((10, u64::MAX - 1)));
+    }
+
+    #[test]
+    fn test_find_most_overlapped_id() {
+        assert_eq!(find_most_overlapped_id(&[]), None);
+        assert_eq!(find_most_overlapped_id(&[(1, 3), (3, 5), (5, 7)]), Some((3, 2)));
+        assert_eq!(find_most_overlapped_id(&[(1, 10), (2, 3), (5, 6), (6, 6)]), Some((6, 3)));
+        assert_eq!(find_most_overlapped_id(&[(4, 4), (4, 4), (u64::MAX, u64::MAX)]), Some((4, 2)));
+    }
+}
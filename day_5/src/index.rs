@@ -0,0 +1,266 @@
+use crate::CafeteriaError::InvalidUpdate;
+use crate::{CafeteriaError, CafeteriaResult, combine_overlapping_ranges, count_ids, parse_range};
+use itertools::Itertools;
+use std::collections::BTreeMap;
+
+/// Mutable set of fresh IDs, kept as disjoint and non-adjacent ranges in a map from range start to range end. Inserting
+/// or removing a range only touches the ranges it overlaps, and lookups are a single map search, so all operations are
+/// logarithmic in the number of stored ranges (plus the number of ranges merged, split or returned).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FreshnessIndex {
+    ranges: BTreeMap<u64, u64>,
+}
+
+/// A single change to or query of a `FreshnessIndex`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Update {
+    Insert((u64, u64)),
+    Remove((u64, u64)),
+    QueryId(u64),
+    QueryRange((u64, u64)),
+}
+
+impl FreshnessIndex {
+    pub fn insert(&mut self, (start, end): (u64, u64)) {
+        let (mut merged_start, mut merged_end) = (start, end);
+
+        // A range starting before the new one can only be merged with it if it reaches up to it.
+        if let Some((&previous_start, &previous_end)) = self.ranges.range(..start).next_back()
+            && previous_end.saturating_add(1) >= start
+        {
+            merged_start = previous_start;
+            merged_end = merged_end.max(previous_end);
+        }
+
+        while let Some((&next_start, &next_end)) = self.ranges.range(merged_start..).next() {
+            if next_start > merged_end.saturating_add(1) {
+                break;
+            }
+
+            self.ranges.remove(&next_start);
+            merged_end = merged_end.max(next_end);
+        }
+
+        self.ranges.insert(merged_start, merged_end);
+    }
+
+    /// Removes all IDs of the range, splitting the stored ranges that are only partially covered by it.
+    pub fn remove(&mut self, (start, end): (u64, u64)) {
+        if let Some((&previous_start, &previous_end)) = self.ranges.range(..start).next_back()
+            && previous_end >= start
+        {
+            self.ranges.insert(previous_start, start - 1);
+
+            if previous_end > end {
+                self.ranges.insert(end + 1, previous_end);
+                return;
+            }
+        }
+
+        let covered_ranges = self.ranges.range(start..=end).map(|(start, end)| (*start, *end)).collect_vec();
+
+        for (covered_start, covered_end) in covered_ranges {
+            self.ranges.remove(&covered_start);
+
+            if covered_end > end {
+                self.ranges.insert(end + 1, covered_end);
+            }
+        }
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.ranges.range(..=id).next_back().is_some_and(|(_, end)| *end >= id)
+    }
+
+    /// The fresh parts of the range, in order.
+    pub fn query_range(&self, (start, end): (u64, u64)) -> Vec<(u64, u64)> {
+        let first_start = match self.ranges.range(..=start).next_back() {
+            Some((previous_start, previous_end)) if *previous_end >= start => *previous_start,
+            _ => start,
+        };
+
+        self.ranges
+            .range(first_start..=end)
+            .map(|(range_start, range_end)| ((*range_start).max(start), (*range_end).min(end)))
+            .collect_vec()
+    }
+
+    /// The stored ranges, sorted and disjoint.
+    pub fn ranges(&self) -> Vec<(u64, u64)> {
+        self.ranges.iter().map(|(start, end)| (*start, *end)).collect_vec()
+    }
+
+    pub fn count_ids(&self) -> u128 {
+        count_ids(&self.ranges())
+    }
+
+    pub fn apply(&mut self, update: Update) -> Option<String> {
+        match update {
+            Update::Insert(range) => self.insert(range),
+            Update::Remove(range) => self.remove(range),
+            Update::QueryId(id) => {
+                let status = if self.contains(id) { "fresh" } else { "spoiled" };
+                return Some(format!("{id}: {status}"));
+            }
+            Update::QueryRange((start, end)) => {
+                let fresh_ranges = self.query_range((start, end));
+                let listed_ranges = fresh_ranges.iter().map(|(start, end)| format!("{start}-{end}")).join(", ");
+
+                return Some(format!("{start}-{end}: {} fresh IDs ({listed_ranges})", count_ids(&fresh_ranges)));
+            }
+        }
+
+        None
+    }
+}
+
+/// Builds the index in bulk, merging the ranges with a single sort-and-sweep pass instead of inserting them one by one.
+impl FromIterator<(u64, u64)> for FreshnessIndex {
+    fn from_iter<T: IntoIterator<Item = (u64, u64)>>(iter: T) -> Self {
+        FreshnessIndex {
+            ranges: combine_overlapping_ranges(iter.into_iter().collect_vec(), true).into_iter().collect(),
+        }
+    }
+}
+
+/// Parses one update per line: `+range` inserts a range, `-range` removes one and `?id` or `?range` queries the
+/// index, with ranges in any syntax accepted by `parse_range`. Blank lines are skipped.
+pub fn parse_updates(data: &str) -> Result<Vec<Update>, Vec<CafeteriaError<'_>>> {
+    let (updates, errors): (Vec<_>, Vec<_>) = data
+        .lines()
+        .enumerate()
+        .map(|(index, text)| (index + 1, text.trim()))
+        .filter(|(_, text)| !text.is_empty())
+        .map(|(line, text)| parse_update(line, text))
+        .partition_result();
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(updates)
+}
+
+/// Range errors are reported as they are, so only lines without a known prefix are an `InvalidUpdate`.
+fn parse_update(line: usize, text: &str) -> CafeteriaResult<'_, Update> {
+    match text.split_at_checked(1) {
+        Some(("+", range)) => Ok(Update::Insert(parse_range(line, range)?)),
+        Some(("-", range)) => Ok(Update::Remove(parse_range(line, range)?)),
+        Some(("?", query)) => match query.parse() {
+            Ok(id) => Ok(Update::QueryId(id)),
+            Err(_) => Ok(Update::QueryRange(parse_range(line, query)?)),
+        },
+        _ => Err(InvalidUpdate { line, text }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CafeteriaError::{AmbiguousRange, EmptyRange, MalformedRange, ReversedRange};
+    use crate::tests::next_random;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_insert() {
+        let mut index = [(10, 14), (3, 5), (16, 20), (12, 18)].into_iter().collect::<FreshnessIndex>();
+        assert_eq!(index.ranges(), vec![(3, 5), (10, 20)]);
+
+        index.insert((6, 8));
+        assert_eq!(index.ranges(), vec![(3, 8), (10, 20)]);
+
+        index.insert((9, 9));
+        assert_eq!(index.ranges(), vec![(3, 20)]);
+
+        index.insert((u64::MAX - 1, u64::MAX));
+        index.insert((0, 1));
+        assert_eq!(index.ranges(), vec![(0, 1), (3, 20), (u64::MAX - 1, u64::MAX)]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut index = [(3, 5), (10, 20), (25, 30)].into_iter().collect::<FreshnessIndex>();
+
+        index.remove((12, 14));
+        assert_eq!(index.ranges(), vec![(3, 5), (10, 11), (15, 20), (25, 30)]);
+
+        index.remove((5, 26));
+        assert_eq!(index.ranges(), vec![(3, 4), (27, 30)]);
+
+        index.remove((0, u64::MAX));
+        assert_eq!(index.ranges(), vec![]);
+    }
+
+    #[test]
+    fn test_queries() {
+        let index = [(3, 5), (10, 20), (u64::MAX, u64::MAX)].into_iter().collect::<FreshnessIndex>();
+
+        assert!(!index.contains(0));
+        assert!(index.contains(3));
+        assert!(index.contains(5));
+        assert!(!index.contains(6));
+        assert!(index.contains(20));
+        assert!(!index.contains(21));
+        assert!(index.contains(u64::MAX));
+        assert!(!FreshnessIndex::default().contains(1));
+
+        assert_eq!(index.query_range((4, 12)), vec![(4, 5), (10, 12)]);
+        assert_eq!(index.query_range((6, 9)), vec![]);
+        assert_eq!(index.query_range((12, 13)), vec![(12, 13)]);
+    }
+
+    #[test]
+    fn test_matches_id_set() {
+        let mut state = 11u64;
+        let mut index = FreshnessIndex::default();
+        let mut ids = BTreeSet::new();
+
+        for _ in 0..2000 {
+            let random = next_random(&mut state);
+            let start = (random >> 33) % 200;
+            let range = (start, start + (random >> 50) % 12);
+
+            if (random >> 20).is_multiple_of(3) {
+                index.remove(range);
+                (range.0..=range.1).for_each(|id| {
+                    ids.remove(&id);
+                });
+            } else {
+                index.insert(range);
+                ids.extend(range.0..=range.1);
+            }
+
+            let ranges = index.ranges();
+            assert_eq!(ranges, combine_overlapping_ranges(ranges.clone(), true));
+            assert_eq!(ranges.iter().flat_map(|(start, end)| *start..=*end).collect::<BTreeSet<_>>(), ids);
+        }
+    }
+
+    #[test]
+    fn test_parse_updates() {
+        assert_eq!(
+            parse_updates("+1-5\n-2-3\n\n?4\n?1-10"),
+            Ok(vec![
+                Update::Insert((1, 5)),
+                Update::Remove((2, 3)),
+                Update::QueryId(4),
+                Update::QueryRange((1, 10)),
+            ])
+        );
+        assert_eq!(
+            parse_updates("+1..5\n-7-\n?9"),
+            Ok(vec![Update::Insert((1, 4)), Update::Remove((7, u64::MAX)), Update::QueryId(9)])
+        );
+        assert_eq!(
+            parse_updates("+x\n*2-3\n?5..5\n--5\n-9-4\n7"),
+            Err(vec![
+                MalformedRange { line: 1, text: "x" },
+                InvalidUpdate { line: 2, text: "*2-3" },
+                EmptyRange { line: 3, text: "5..5" },
+                AmbiguousRange { line: 4, text: "-5" },
+                ReversedRange { line: 5, start: 9, end: 4 },
+                InvalidUpdate { line: 6, text: "7" },
+            ])
+        );
+    }
+}
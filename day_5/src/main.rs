@@ -1,21 +1,216 @@
-use itertools::{Itertools, sorted, merge};
-use std::cmp::{max, min};
-use std::io::{Read, stdin};
+mod algebra;
+mod coverage;
+mod index;
+mod weights;
+
+use crate::CafeteriaError::{
+    AmbiguousRange, EmptyRange, InvalidIngredientId, InvalidWeight, MalformedRange, MisplacedIngredientId,
+    MisplacedRange, ReversedRange, UnexpectedSection,
+};
+use crate::algebra::{SetOperation, render_set_operation};
+use crate::coverage::{analyze_coverage, render_coverage_report};
+use crate::index::{FreshnessIndex, parse_updates};
+use crate::weights::{count_ids_covered_at_least, render_ingredient_weights, weigh_ingredients};
+use itertools::Itertools;
+use shared::{ArgumentError, parse_option_value, read_data_file, read_input};
+use std::cmp::{Ordering, Reverse, max};
+use std::collections::{BTreeSet, BinaryHeap};
 
 const DAY: u8 = 5;
 
+type CafeteriaResult<'a, T> = Result<T, CafeteriaError<'a>>;
+
+/// Parse errors, with 1-based line numbers.
+#[derive(Debug, PartialEq)]
+enum CafeteriaError<'a> {
+    /// A line among the fresh ranges that isn't a range of IDs (with an optional weight and label).
+    MalformedRange { line: usize, text: &'a str },
+    /// A range that could be read in more than one way, like `1-2-3`, `-5` or `5..`.
+    AmbiguousRange { line: usize, text: &'a str },
+    /// A half-open range without any IDs, like `5..5`.
+    EmptyRange { line: usize, text: &'a str },
+    /// A range weight that isn't `*` followed by a number.
+    InvalidWeight { line: usize, text: &'a str },
+    /// A range whose start comes after its end.
+    ReversedRange { line: usize, start: u64, end: u64 },
+    /// A line among the available ingredients that isn't an ID.
+    InvalidIngredientId { line: usize, text: &'a str },
+    /// A range below the blank line, among the available ingredients.
+    MisplacedRange { line: usize, text: &'a str },
+    /// A single ID in input without any blank line, which is more likely an ingredient below a missing separator than
+    /// a single-ID range. Such ranges can still be written as `id-id` there.
+    MisplacedIngredientId { line: usize, text: &'a str },
+    /// A third section after the available ingredients.
+    UnexpectedSection { line: usize },
+    /// A line of an update file that isn't an insertion, removal or query.
+    InvalidUpdate { line: usize, text: &'a str },
+}
+
 #[derive(Debug, PartialEq)]
 struct CafeteriaData {
-    pub fresh_ranges: Vec<(u64, u64)>,
+    pub fresh_ranges: Vec<FreshRange>,
     pub available_ingredients: Vec<u64>,
 }
 
+impl CafeteriaData {
+    /// The bounds of the fresh ranges, in input order.
+    fn range_bounds(&self) -> Vec<(u64, u64)> {
+        self.fresh_ranges.iter().map(|range| range.bounds).collect_vec()
+    }
+}
+
+/// A fresh range, along with the input line it comes from and the weight and label that may follow it on that line.
+#[derive(Debug, Clone, PartialEq)]
+struct FreshRange {
+    /// Inclusive start and end.
+    bounds: (u64, u64),
+    line: usize,
+    /// How much the range counts for, like the number of suppliers vouching for it. Defaults to 1.
+    weight: u64,
+    label: Option<String>,
+}
+
+/// Freshness of an available ingredient, traced back to the input lines of the fresh ranges containing it.
+#[derive(Debug, PartialEq)]
+struct IngredientReport {
+    ingredient: u64,
+    /// Empty for spoiled ingredients.
+    source_lines: Vec<usize>,
+}
+
+/// How ingredients are looked up in the merged fresh ranges.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum QueryMode {
+    /// A binary search per ingredient.
+    #[default]
+    BinarySearch,
+    /// Sorts the ingredients and walks them alongside the ranges in one pass.
+    MergeWalk,
+    /// A search of the interval index per ingredient.
+    IndexLookup,
+}
+
+#[derive(Debug, PartialEq)]
+struct Arguments {
+    input_path: Option<String>,
+    query_mode: QueryMode,
+    report: bool,
+    /// File with the fresh ranges to compare against.
+    compare_path: Option<String>,
+    /// Set operations to run against the compared ranges. Empty means all of them.
+    operations: Vec<SetOperation>,
+    coverage: bool,
+    /// Interval to look for gaps in. Defaults to the span of the fresh ranges.
+    bounds: Option<(u64, u64)>,
+    weights: bool,
+    /// Count the IDs contained in at least this many fresh ranges.
+    min_ranges: Option<usize>,
+    /// File with insertions, removals and queries to run against an index of the fresh ranges.
+    updates_path: Option<String>,
+}
+
 fn main() {
-    let data = shared::read_data(DAY);
+    let arguments = match parse_arguments(std::env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(error) => {
+            eprintln!("Invalid arguments: {error:?}");
+            eprintln!(
+                "Usage: day_5 [INPUT|-] [--batch|--index] [--report] [--compare FILE] [--operation OPERATION]... \
+                 [--coverage] [--bounds LOW-HIGH] [--updates FILE] [--weights] [--at-least K]"
+            );
+            eprintln!("Operations: union, intersection, difference, symmetric-difference");
+            return;
+        }
+    };
+
+    let data = read_input(DAY, arguments.input_path.as_deref());
+
+    let parsed = match parse_input(&data) {
+        Ok(parsed) => parsed,
+        Err(errors) => {
+            eprintln!("Errors occurred while parsing the input: {errors:#?}");
+            return;
+        }
+    };
+
+    if arguments.report {
+        println!("Available ingredients:");
+        println!("{}", render_ingredient_reports(&explain_ingredients(&parsed)));
+        println!();
+    }
 
-    let parsed = parse_input(&data);
+    if arguments.weights {
+        println!("Ingredient weights:");
+        println!("{}", render_ingredient_weights(&weigh_ingredients(&parsed)));
+        println!();
+    }
+
+    if let Some(min_ranges) = arguments.min_ranges {
+        let covered_ids = count_ids_covered_at_least(&parsed.range_bounds(), min_ranges);
+        println!("IDs in at least {min_ranges} fresh ranges: {covered_ids}");
+        println!();
+    }
+
+    if let Some(compare_path) = &arguments.compare_path {
+        let compare_data = read_data_file(compare_path);
+        let compared = match parse_range_list(&compare_data) {
+            Ok(compared) => compared,
+            Err(errors) => {
+                eprintln!("Errors occurred while parsing {compare_path}: {errors:#?}");
+                return;
+            }
+        };
+
+        let ranges = combine_overlapping_ranges(parsed.range_bounds(), true);
+        let compared_bounds = compared.iter().map(|range| range.bounds).collect_vec();
+        let compared_ranges = combine_overlapping_ranges(compared_bounds, true);
+        let operations = if arguments.operations.is_empty() {
+            SetOperation::ALL.to_vec()
+        } else {
+            arguments.operations.clone()
+        };
 
-    let available_fresh_ingredients = find_available_fresh_ingredients(&parsed);
+        println!("Comparison with {compare_path}:");
+        for operation in operations {
+            println!("{}", render_set_operation(operation, &operation.apply(&ranges, &compared_ranges)));
+        }
+        println!();
+    }
+
+    if arguments.coverage {
+        let span = parsed
+            .range_bounds()
+            .into_iter()
+            .reduce(|(low, high), (start, end)| (low.min(start), high.max(end)));
+
+        match arguments.bounds.or(span) {
+            Some(bounds) => println!("{}", render_coverage_report(&analyze_coverage(&parsed.range_bounds(), bounds))),
+            None => println!("No fresh ranges to find the gaps between (use --bounds LOW-HIGH)"),
+        }
+        println!();
+    }
+
+    if let Some(updates_path) = &arguments.updates_path {
+        let updates_data = read_data_file(updates_path);
+        let updates = match parse_updates(&updates_data) {
+            Ok(updates) => updates,
+            Err(errors) => {
+                eprintln!("Errors occurred while parsing {updates_path}: {errors:#?}");
+                return;
+            }
+        };
+
+        let mut index = parsed.range_bounds().into_iter().collect::<FreshnessIndex>();
+
+        println!("Updates from {updates_path}:");
+        for answer in updates.into_iter().filter_map(|update| index.apply(update)) {
+            println!("{answer}");
+        }
+        println!("Fresh IDs after the updates: {}", index.count_ids());
+        println!();
+    }
+
+    let available_fresh_ingredients = find_available_fresh_ingredients(&parsed, arguments.query_mode);
     println!(
         "Available fresh ingredients: {}",
         available_fresh_ingredients.len()
@@ -26,65 +221,214 @@ fn main() {
 
 }
 
-fn find_available_fresh_ingredients(data: &CafeteriaData) -> Vec<u64> {
+fn parse_arguments(mut args: impl Iterator<Item = String>) -> Result<Arguments, ArgumentError> {
+    let mut arguments = Arguments {
+        input_path: None,
+        query_mode: QueryMode::default(),
+        report: false,
+        compare_path: None,
+        operations: Vec::new(),
+        coverage: false,
+        bounds: None,
+        weights: false,
+        min_ranges: None,
+        updates_path: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--batch" => arguments.query_mode = QueryMode::MergeWalk,
+            "--index" => arguments.query_mode = QueryMode::IndexLookup,
+            "--report" => arguments.report = true,
+            "--compare" => arguments.compare_path = Some(parse_option_value(&arg, &mut args)?),
+            "--operation" => arguments.operations.push(parse_option_value(&arg, &mut args)?),
+            "--coverage" => arguments.coverage = true,
+            "--weights" => arguments.weights = true,
+            "--at-least" => arguments.min_ranges = Some(parse_option_value(&arg, &mut args)?),
+            "--updates" => arguments.updates_path = Some(parse_option_value(&arg, &mut args)?),
+            "--bounds" => {
+                let value: String = parse_option_value(&arg, &mut args)?;
+                let bounds = parse_range(0, &value).map_err(|_| ArgumentError::InvalidValue {
+                    option: arg.clone(),
+                    value: value.clone(),
+                })?;
+
+                arguments.coverage = true;
+                arguments.bounds = Some(bounds);
+            }
+            option if option.starts_with("--") => return Err(ArgumentError::UnknownOption(arg)),
+            _ if arguments.input_path.is_none() => arguments.input_path = Some(arg),
+            _ => return Err(ArgumentError::UnexpectedArgument(arg)),
+        }
+    }
+
+    // Operations need the ranges to compare against.
+    if !arguments.operations.is_empty() && arguments.compare_path.is_none() {
+        return Err(ArgumentError::MissingValue("--compare".to_string()));
+    }
+
+    if arguments.min_ranges == Some(0) {
+        return Err(ArgumentError::InvalidValue {
+            option: "--at-least".to_string(),
+            value: "0".to_string(),
+        });
+    }
+
+    Ok(arguments)
+}
+
+/// Returns the available ingredients that are in any of the fresh ranges, in their original order.
+fn find_available_fresh_ingredients(data: &CafeteriaData, query_mode: QueryMode) -> Vec<u64> {
+    let merged_ranges = combine_overlapping_ranges(data.range_bounds(), true);
+
+    match query_mode {
+        QueryMode::BinarySearch => data
+            .available_ingredients
+            .iter()
+            .filter(|ingredient| is_fresh(&merged_ranges, **ingredient))
+            .copied()
+            .collect_vec(),
+        QueryMode::IndexLookup => {
+            let index = merged_ranges.into_iter().collect::<FreshnessIndex>();
+
+            data.available_ingredients
+                .iter()
+                .filter(|ingredient| index.contains(**ingredient))
+                .copied()
+                .collect_vec()
+        }
+        QueryMode::MergeWalk => {
+            let freshness = find_freshness_batch(&merged_ranges, &data.available_ingredients);
+
+            data.available_ingredients
+                .iter()
+                .zip(freshness)
+                .filter(|(_, fresh)| *fresh)
+                .map(|(ingredient, _)| *ingredient)
+                .collect_vec()
+        }
+    }
+}
+
+fn explain_ingredients(data: &CafeteriaData) -> Vec<IngredientReport> {
     data.available_ingredients
         .iter()
-        .filter(|ingredient| {
-            data.fresh_ranges
-                .iter()
-                .any(|(start, end)| start <= ingredient && *ingredient <= end)
+        .zip(find_containing_ranges(data))
+        .map(|(ingredient, range_indices)| IngredientReport {
+            ingredient: *ingredient,
+            source_lines: range_indices.iter().map(|index| data.fresh_ranges[*index].line).collect_vec(),
         })
-        .map(|ingredient| *ingredient)
         .collect_vec()
 }
 
-fn count_all_fresh_ingredients(data: CafeteriaData) -> u64 {
-    let merged_ranges = combine_overlapping_ranges(data.fresh_ranges);
-    let total_count = merged_ranges.iter().fold(0, |acc, range| acc + (range.1 - range.0) + 1);
+/// Finds the fresh ranges containing each available ingredient, as indices into `fresh_ranges` in input order.
+///
+/// Instead of checking every range for every ingredient, this sweeps over the ingredients in sorted order. A range
+/// becomes active once the sweep reaches its start and is dropped once the sweep passes its end, so the whole sweep
+/// takes O((ingredients + ranges) × log ranges) on top of the size of the result.
+fn find_containing_ranges(data: &CafeteriaData) -> Vec<Vec<usize>> {
+    let ranges = &data.fresh_ranges;
+    let ingredients = &data.available_ingredients;
+
+    let mut containing_ranges = vec![Vec::new(); ingredients.len()];
+    let mut starting_ranges = (0..ranges.len()).sorted_unstable_by_key(|index| ranges[*index].bounds.0).peekable();
+    let mut ending_ranges = BinaryHeap::new();
+    let mut active_ranges = BTreeSet::new();
+
+    for index in (0..ingredients.len()).sorted_unstable_by_key(|index| ingredients[*index]) {
+        let ingredient = ingredients[index];
+
+        while let Some(range_index) = starting_ranges.next_if(|next| ranges[*next].bounds.0 <= ingredient) {
+            ending_ranges.push(Reverse((ranges[range_index].bounds.1, range_index)));
+            active_ranges.insert(range_index);
+        }
+
+        while let Some(Reverse((end, range_index))) = ending_ranges.peek()
+            && *end < ingredient
+        {
+            active_ranges.remove(range_index);
+            ending_ranges.pop();
+        }
+
+        containing_ranges[index] = active_ranges.iter().copied().collect_vec();
+    }
+
+    containing_ranges
+}
+
+fn render_ingredient_reports(reports: &[IngredientReport]) -> String {
+    let width = reports.iter().map(|report| report.ingredient.to_string().len()).max().unwrap_or(0);
 
-    total_count
+    reports
+        .iter()
+        .map(|report| {
+            if report.source_lines.is_empty() {
+                format!("{:>width$}: spoiled", report.ingredient)
+            } else {
+                format!("{:>width$}: fresh (lines {})", report.ingredient, report.source_lines.iter().join(", "))
+            }
+        })
+        .join("\n")
 }
 
+/// Looks up an ingredient in sorted, disjoint ranges (as returned by `combine_overlapping_ranges`) in O(log n).
+fn is_fresh(merged_ranges: &[(u64, u64)], ingredient: u64) -> bool {
+    let index = merged_ranges.partition_point(|(_, end)| *end < ingredient);
 
-fn combine_overlapping_ranges(mut ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
-    let mut iteration = 0;
+    merged_ranges
+        .get(index)
+        .is_some_and(|(start, _)| *start <= ingredient)
+}
 
-    loop {
-        println!("Iteration: {}", iteration);
-        iteration += 1;
+/// Tells for every ingredient whether it's fresh, given sorted, disjoint ranges. The ingredients are visited in sorted
+/// order, so a single pointer moving forward through the ranges answers all of them.
+fn find_freshness_batch(merged_ranges: &[(u64, u64)], ingredients: &[u64]) -> Vec<bool> {
+    let mut freshness = vec![false; ingredients.len()];
+    let mut ranges = merged_ranges.iter().peekable();
 
-        let (updated_count, combined) = combine_overlapping_ranges_single_iteration(ranges.clone());
+    for index in (0..ingredients.len()).sorted_unstable_by_key(|index| ingredients[*index]) {
+        let ingredient = ingredients[index];
 
-        if updated_count == 0 {
-            break;
-        }
+        while ranges.next_if(|(_, end)| *end < ingredient).is_some() {}
 
-        ranges = combined;
+        freshness[index] = ranges.peek().is_some_and(|(start, _)| *start <= ingredient);
     }
 
-    ranges
+    freshness
+}
+
+fn count_all_fresh_ingredients(data: CafeteriaData) -> u128 {
+    data.range_bounds().into_iter().collect::<FreshnessIndex>().count_ids()
 }
 
-fn combine_overlapping_ranges_single_iteration(ranges: Vec<(u64, u64)>) -> (u64, Vec<(u64, u64)>) {
+/// Counts the IDs in disjoint ranges. The count is a `u128` since `0-18446744073709551615` alone already holds one ID
+/// more than a `u64` can count.
+fn count_ids(ranges: &[(u64, u64)]) -> u128 {
+    ranges.iter().map(|(start, end)| (end - start) as u128 + 1).sum()
+}
 
-    let mut combined: Vec<(u64, u64)> = Vec::new();
-    let mut updated_count = 0;
+/// Merges the ranges into the sorted list of disjoint ranges covering the same IDs, by sorting them by their start
+/// and sweeping over them once. With `merge_adjacent`, ranges that only touch (like `3-5` and `6-9`) are merged too.
+fn combine_overlapping_ranges(mut ranges: Vec<(u64, u64)>, merge_adjacent: bool) -> Vec<(u64, u64)> {
+    ranges.sort_unstable();
 
-    for range in ranges {
-        match combined.iter_mut().find(|existing| ranges_overlap(&range, *existing)) {
-            None => {
-                combined.push(range)
-            }
-            Some((start, end)) => {
-                updated_count += 1;
+    let mut combined: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
 
-                *start = min(range.0, *start);
-                *end = max(range.1, *end);
+    for range in ranges {
+        match combined.last_mut() {
+            Some(last) if ranges_overlap(last, &range) || (merge_adjacent && ranges_adjacent(last, &range)) => {
+                last.1 = max(last.1, range.1);
             }
+            _ => combined.push(range),
         }
     }
 
-    (updated_count, combined)
+    combined
+}
+
+/// Whether `b` starts right after `a` ends.
+fn ranges_adjacent(a: &(u64, u64), b: &(u64, u64)) -> bool {
+    a.1.checked_add(1) == Some(b.0)
 }
 
 fn ranges_overlap(a: &(u64, u64), b: &(u64, u64)) -> bool {
@@ -99,37 +443,162 @@ fn ranges_overlap(a: &(u64, u64), b: &(u64, u64)) -> bool {
     false
 }
 
-// Technically not the full format spec, but (assuming correct input data) it's close enough and I want to
-// complete this day faster than usual.
-fn parse_input(data: &str) -> CafeteriaData {
-    let (range_lines, available_lines): (Vec<_>, Vec<_>) = data
+/// Parses the fresh ranges, a blank line and the available ingredient IDs.
+fn parse_input(data: &str) -> Result<CafeteriaData, Vec<CafeteriaError<'_>>> {
+    let mut sections = data
         .lines()
-        .filter(|l| !l.trim().is_empty())
-        .partition(|l| l.contains("-"));
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .chunk_by(|(_, line)| line.is_empty())
+        .into_iter()
+        .filter(|(blank, _)| !blank)
+        .map(|(_, lines)| lines.collect_vec())
+        .collect_vec();
+
+    // Input starting with the separator has no fresh ranges.
+    if data.lines().next().is_some_and(|line| line.trim().is_empty()) {
+        sections.insert(0, Vec::new());
+    }
+
+    let has_separator = data.lines().any(|line| line.trim().is_empty());
 
-    let fresh_ranges = range_lines
+    let mut sections = sections.into_iter();
+    let range_lines = sections.next().unwrap_or_default();
+    let ingredient_lines = sections.next().unwrap_or_default();
+
+    let (fresh_ranges, range_errors): (Vec<_>, Vec<_>) = range_lines
         .into_iter()
-        .map(|l| {
-            let split = l.split("-").collect_vec();
-            (
-                split[0].parse::<u64>().unwrap(),
-                split[1].parse::<u64>().unwrap(),
-            )
+        .map(|(line, text)| match parse_range_line(line, text) {
+            Ok(_) if !has_separator && text.parse::<u64>().is_ok() => Err(MisplacedIngredientId { line, text }),
+            range => range,
         })
-        .collect_vec();
+        .partition_result();
 
-    let available_ingredients = available_lines
+    let (available_ingredients, ingredient_errors): (Vec<_>, Vec<_>) = ingredient_lines
         .into_iter()
-        .map(|l| l.parse::<u64>().unwrap())
-        .collect_vec();
+        .map(|(line, text)| match (text.parse::<u64>(), parse_range_line(line, text)) {
+            (Ok(ingredient), _) => Ok(ingredient),
+            (Err(_), Ok(_)) => Err(MisplacedRange { line, text }),
+            (Err(_), Err(_)) => Err(InvalidIngredientId { line, text }),
+        })
+        .partition_result();
 
-    println!("ranges: {fresh_ranges:?}");
-    println!("available ingredients: {available_ingredients:?}");
+    let section_errors = sections.map(|lines| UnexpectedSection { line: lines[0].0 });
+
+    let errors = range_errors
+        .into_iter()
+        .chain(ingredient_errors)
+        .chain(section_errors)
+        .collect_vec();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
 
-    CafeteriaData {
+    Ok(CafeteriaData {
         fresh_ranges,
         available_ingredients,
+    })
+}
+
+/// Parses a plain list of fresh range lines, without any ingredient IDs, skipping blank lines.
+fn parse_range_list(data: &str) -> Result<Vec<FreshRange>, Vec<CafeteriaError<'_>>> {
+    let (fresh_ranges, errors): (Vec<_>, Vec<_>) = data
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(line, text)| parse_range_line(line, text))
+        .partition_result();
+
+    if !errors.is_empty() {
+        return Err(errors);
     }
+
+    Ok(fresh_ranges)
+}
+
+/// Parses a fresh range line: a range (see `parse_range`), optionally followed by a `*weight` and then a label. The
+/// label and weight are split off from the end of the line, so the range itself may contain spaces (like `3 - 5`).
+/// Labels can't start with a digit or any of `-.=*`, as those could just as well be the end of the range, and can't
+/// follow an open range separator (like `3 - x`), as the label could just as well be a mistyped end.
+fn parse_range_line(line: usize, text: &str) -> CafeteriaResult<'_, FreshRange> {
+    let is_label = |field: &str| !field.starts_with(|symbol: char| symbol.is_ascii_digit() || "-.=*".contains(symbol));
+    let mut range_text = text;
+
+    let label = match split_last_field(range_text) {
+        Some((rest, field)) if is_label(field) => {
+            if rest.trim_end().ends_with(['-', '.', '=']) {
+                return Err(AmbiguousRange { line, text });
+            }
+            range_text = rest;
+            Some(field.to_string())
+        }
+        _ => None,
+    };
+
+    let weight = match split_last_field(range_text) {
+        Some((rest, field)) if field.starts_with('*') => {
+            range_text = rest;
+            field[1..].parse().map_err(|_| InvalidWeight { line, text: field })?
+        }
+        _ => 1,
+    };
+
+    // Any other fields are left in the range text, so the whole line is what's malformed.
+    let bounds = parse_range(line, range_text).map_err(|error| match error {
+        MalformedRange { .. } => MalformedRange { line, text },
+        error => error,
+    })?;
+
+    Ok(FreshRange {
+        bounds,
+        line,
+        weight,
+        label,
+    })
+}
+
+/// Splits the last whitespace separated field off the text.
+fn split_last_field(text: &str) -> Option<(&str, &str)> {
+    text.trim_end().rsplit_once(char::is_whitespace)
+}
+
+/// Parses a range of IDs into its inclusive bounds. Accepts `start-end` and `start..=end` (both inclusive),
+/// `start..end` (half-open), `start-` (from `start` up to the largest ID) and a single ID.
+fn parse_range(line: usize, text: &str) -> CafeteriaResult<'_, (u64, u64)> {
+    let text = text.trim();
+    let separator_count = text.matches("..").count() + text.matches('-').count();
+
+    // A leading `-` could be a negative ID, and `start..` could be a half-open range with its end cut off.
+    if separator_count > 1 || text.starts_with(['-', '.']) || text.ends_with("..") {
+        return Err(AmbiguousRange { line, text });
+    }
+
+    let parse_id = |id: &str| id.trim().parse::<u64>().map_err(|_| MalformedRange { line, text });
+
+    let (start, end) = if let Some((start, end)) = text.split_once("..=") {
+        (parse_id(start)?, parse_id(end)?)
+    } else if let Some((start, end)) = text.split_once("..") {
+        let (start, end) = (parse_id(start)?, parse_id(end)?);
+
+        match start.cmp(&end) {
+            Ordering::Less => (start, end - 1),
+            Ordering::Equal => return Err(EmptyRange { line, text }),
+            Ordering::Greater => return Err(ReversedRange { line, start, end }),
+        }
+    } else if let Some((start, end)) = text.split_once('-') {
+        let end = if end.trim().is_empty() { u64::MAX } else { parse_id(end)? };
+        (parse_id(start)?, end)
+    } else {
+        let id = parse_id(text)?;
+        (id, id)
+    };
+
+    if start > end {
+        return Err(ReversedRange { line, start, end });
+    }
+
+    Ok((start, end))
 }
 
 #[cfg(test)]
@@ -138,33 +607,334 @@ mod tests {
 
     const EXAMPLE_INPUT: &str = "3-5\n10-14\n16-20\n12-18\n\n1\n5\n8\n11\n17\n32";
 
+    /// Advances the state of a small linear congruential generator and returns it, for reproducible random tests.
+    /// The low bits repeat quickly, so callers should take the high bits.
+    pub(crate) fn next_random(state: &mut u64) -> u64 {
+        *state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        *state
+    }
+
+    /// Unweighted, unlabelled ranges on consecutive lines from line 1 on.
+    fn unweighted_ranges(bounds: &[(u64, u64)]) -> Vec<FreshRange> {
+        bounds
+            .iter()
+            .zip(1..)
+            .map(|(bounds, line)| FreshRange {
+                bounds: *bounds,
+                line,
+                weight: 1,
+                label: None,
+            })
+            .collect_vec()
+    }
+
     #[test]
     fn test_parse_input() {
         assert_eq!(
             parse_input(EXAMPLE_INPUT),
-            CafeteriaData {
-                fresh_ranges: vec![(3, 5), (10, 14), (16, 20), (12, 18)],
+            Ok(CafeteriaData {
+                fresh_ranges: unweighted_ranges(&[(3, 5), (10, 14), (16, 20), (12, 18)]),
                 available_ingredients: vec![1, 5, 8, 11, 17, 32]
-            }
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_input_sections() {
+        assert_eq!(
+            parse_input("\n1\n2\n"),
+            Ok(CafeteriaData {
+                fresh_ranges: vec![],
+                available_ingredients: vec![1, 2]
+            })
+        );
+        assert_eq!(
+            parse_input("1-2\n\n\n 3 \n\n"),
+            Ok(CafeteriaData {
+                fresh_ranges: unweighted_ranges(&[(1, 2)]),
+                available_ingredients: vec![3]
+            })
+        );
+        assert_eq!(
+            parse_input("1-2\n3\n\n4\n5-6\n\n7"),
+            Err(vec![
+                MisplacedRange { line: 5, text: "5-6" },
+                UnexpectedSection { line: 7 },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_input_missing_separator() {
+        assert_eq!(
+            parse_input("3-5\n10-14\n1\n5\n12"),
+            Err(vec![
+                MisplacedIngredientId { line: 3, text: "1" },
+                MisplacedIngredientId { line: 4, text: "5" },
+                MisplacedIngredientId { line: 5, text: "12" },
+            ])
+        );
+        assert_eq!(parse_input("3-5\n7-7").map(|data| data.range_bounds()), Ok(vec![(3, 5), (7, 7)]));
+        assert_eq!(parse_input("3-5\n7\n\n").map(|data| data.range_bounds()), Ok(vec![(3, 5), (7, 7)]));
+    }
+
+    #[test]
+    fn test_parse_range_syntaxes() {
+        assert_eq!(parse_range(1, "3-5"), Ok((3, 5)));
+        assert_eq!(parse_range(1, "3..=5"), Ok((3, 5)));
+        assert_eq!(parse_range(1, "3..5"), Ok((3, 4)));
+        assert_eq!(parse_range(1, "3..4"), Ok((3, 3)));
+        assert_eq!(parse_range(1, "3-"), Ok((3, u64::MAX)));
+        assert_eq!(parse_range(1, "18446744073709551615-"), Ok((u64::MAX, u64::MAX)));
+        assert_eq!(parse_range(1, "7"), Ok((7, 7)));
+    }
+
+    #[test]
+    fn test_parse_range_errors() {
+        for text in ["1-2-3", "1-2..3", "1..2..3", "-5", "..5", "..=5", "5..", "1--2"] {
+            assert_eq!(parse_range(4, text), Err(AmbiguousRange { line: 4, text }), "{text}");
+        }
+
+        assert_eq!(parse_range(4, "5..5"), Err(EmptyRange { line: 4, text: "5..5" }));
+        assert_eq!(parse_range(4, "6..5"), Err(ReversedRange { line: 4, start: 6, end: 5 }));
+        assert_eq!(parse_range(4, "6..=5"), Err(ReversedRange { line: 4, start: 6, end: 5 }));
+        assert_eq!(parse_range(4, "1...5"), Err(MalformedRange { line: 4, text: "1...5" }));
+        assert_eq!(parse_range(4, "5..="), Err(MalformedRange { line: 4, text: "5..=" }));
+        assert_eq!(parse_range(4, "x"), Err(MalformedRange { line: 4, text: "x" }));
+    }
+
+    #[test]
+    fn test_parse_input_range_syntaxes() {
+        let data = parse_input("3..6\n10..=14\n16\n12- *2\n 3 .. 5 \n\n1").unwrap();
+
+        assert_eq!(data.range_bounds(), vec![(3, 5), (10, 14), (16, 16), (12, u64::MAX), (3, 4)]);
+        assert_eq!(data.fresh_ranges[3].weight, 2);
+        assert_eq!(count_all_fresh_ingredients(data), 3 + (u64::MAX - 10 + 1) as u128);
+    }
+
+    #[test]
+    fn test_parse_weighted_ranges() {
+        let data = parse_input("1-2 *3 acme\n4-5 bob\n6-7 *0\n\n1").unwrap();
+
+        assert_eq!(
+            data.fresh_ranges,
+            vec![
+                FreshRange {
+                    bounds: (1, 2),
+                    line: 1,
+                    weight: 3,
+                    label: Some("acme".to_string()),
+                },
+                FreshRange {
+                    bounds: (4, 5),
+                    line: 2,
+                    weight: 1,
+                    label: Some("bob".to_string()),
+                },
+                FreshRange {
+                    bounds: (6, 7),
+                    line: 3,
+                    weight: 0,
+                    label: None,
+                },
+            ]
+        );
+        assert_eq!(
+            parse_input("1-2 *x\n3-4 acme bob\n5-6 acme *2"),
+            Err(vec![
+                InvalidWeight { line: 1, text: "*x" },
+                MalformedRange { line: 2, text: "3-4 acme bob" },
+                MalformedRange { line: 3, text: "5-6 acme *2" },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_spaced_ranges() {
+        let data = parse_input("3 - 5\n10 -14 *2 acme\n 16 ..= 20  bob\n7 -\n\n1").unwrap();
+
+        assert_eq!(data.range_bounds(), vec![(3, 5), (10, 14), (16, 20), (7, u64::MAX)]);
+        assert_eq!(
+            data.fresh_ranges.iter().map(|range| (range.weight, range.label.as_deref())).collect_vec(),
+            vec![(1, None), (2, Some("acme")), (1, Some("bob")), (1, None)]
+        );
+        assert_eq!(
+            parse_input("10 14\n3 - 5 - 7 acme\n1 2 *3\n3 - x\n3- acme\n3..= bob"),
+            Err(vec![
+                MalformedRange { line: 1, text: "10 14" },
+                AmbiguousRange { line: 2, text: "3 - 5 - 7" },
+                MalformedRange { line: 3, text: "1 2 *3" },
+                AmbiguousRange { line: 4, text: "3 - x" },
+                AmbiguousRange { line: 5, text: "3- acme" },
+                AmbiguousRange { line: 6, text: "3..= bob" },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_operations() {
+        let parse = |args: &[&str]| parse_arguments(args.iter().map(|arg| arg.to_string()));
+
+        assert_eq!(parse(&["--operation", "union"]), Err(ArgumentError::MissingValue("--compare".to_string())));
+        assert_eq!(
+            parse(&["--compare", "other.txt", "--operation", "union"]).map(|arguments| arguments.operations),
+            Ok(vec![SetOperation::Union])
+        );
+    }
+
+    #[test]
+    fn test_parse_range_list() {
+        let ranges = parse_range_list("3-5\n7\n\n10..=12 *2 acme\n").unwrap();
+        assert_eq!(ranges.iter().map(|range| range.bounds).collect_vec(), vec![(3, 5), (7, 7), (10, 12)]);
+        assert_eq!(ranges[2].label.as_deref(), Some("acme"));
+
+        assert_eq!(
+            parse_range_list("3-5\n7-x\n9-4"),
+            Err(vec![MalformedRange { line: 2, text: "7-x" }, ReversedRange { line: 3, start: 9, end: 4 }])
+        );
+    }
+
+    #[test]
+    fn test_parse_input_invalid_lines() {
+        assert_eq!(
+            parse_input("1-2\n3-x\n9-4\n12\n\n5\nfive\n-1"),
+            Err(vec![
+                MalformedRange { line: 2, text: "3-x" },
+                ReversedRange { line: 3, start: 9, end: 4 },
+                InvalidIngredientId { line: 7, text: "five" },
+                InvalidIngredientId { line: 8, text: "-1" },
+            ])
         );
     }
 
     #[test]
     fn test_find_available_fresh_ingredients() {
-        let parsed = parse_input(EXAMPLE_INPUT);
-        assert_eq!(find_available_fresh_ingredients(&parsed), vec![5, 11, 17])
+        let parsed = parse_input(EXAMPLE_INPUT).unwrap();
+        assert_eq!(find_available_fresh_ingredients(&parsed, QueryMode::BinarySearch), vec![5, 11, 17]);
+        assert_eq!(find_available_fresh_ingredients(&parsed, QueryMode::MergeWalk), vec![5, 11, 17]);
+        assert_eq!(find_available_fresh_ingredients(&parsed, QueryMode::IndexLookup), vec![5, 11, 17]);
+    }
+
+    #[test]
+    fn test_is_fresh() {
+        let merged_ranges = [(3, 5), (10, 20), (u64::MAX, u64::MAX)];
+
+        assert!(!is_fresh(&merged_ranges, 0));
+        assert!(is_fresh(&merged_ranges, 3));
+        assert!(is_fresh(&merged_ranges, 5));
+        assert!(!is_fresh(&merged_ranges, 6));
+        assert!(is_fresh(&merged_ranges, 20));
+        assert!(!is_fresh(&merged_ranges, 21));
+        assert!(is_fresh(&merged_ranges, u64::MAX));
+        assert!(!is_fresh(&[], 1));
+    }
+
+    #[test]
+    fn test_explain_ingredients() {
+        let parsed = parse_input(EXAMPLE_INPUT).unwrap();
+        let reports = explain_ingredients(&parsed);
+
+        assert_eq!(
+            reports.iter().map(|report| &report.source_lines).collect_vec(),
+            vec![&vec![], &vec![1], &vec![], &vec![2], &vec![3, 4], &vec![]]
+        );
+        assert_eq!(
+            render_ingredient_reports(&reports),
+            " 1: spoiled\n 5: fresh (lines 1)\n 8: spoiled\n11: fresh (lines 2)\n17: fresh (lines 3, 4)\n32: spoiled"
+        );
+    }
+
+    #[test]
+    fn test_find_containing_ranges() {
+        let data = CafeteriaData {
+            fresh_ranges: unweighted_ranges(&[(5, 9), (1, 3), (3, 3), (8, u64::MAX), (0, 1)]),
+            available_ingredients: vec![3, 10, 0, 4, u64::MAX, 8, 3],
+        };
+
+        assert_eq!(
+            find_containing_ranges(&data),
+            vec![vec![1, 2], vec![3], vec![4], vec![], vec![3], vec![0, 3], vec![1, 2]]
+        );
+    }
+
+    #[test]
+    fn test_find_containing_ranges_matches_scan() {
+        let mut state = 3u64;
+        let mut random_below = |limit: u64| (next_random(&mut state) >> 33) % limit;
+
+        let bounds = (0..200)
+            .map(|_| {
+                let start = random_below(500);
+                (start, start + random_below(40))
+            })
+            .collect_vec();
+        let data = CafeteriaData {
+            fresh_ranges: unweighted_ranges(&bounds),
+            available_ingredients: (0..300).map(|_| random_below(560)).collect_vec(),
+        };
+
+        let scanned = data
+            .available_ingredients
+            .iter()
+            .map(|id| bounds.iter().positions(|(start, end)| start <= id && id <= end).collect_vec())
+            .collect_vec();
+        assert_eq!(find_containing_ranges(&data), scanned);
+    }
+
+    #[test]
+    fn test_find_freshness_batch() {
+        assert_eq!(
+            find_freshness_batch(&[(3, 5), (10, 20)], &[21, 4, 10, 4, 0, 6]),
+            vec![false, true, true, true, false, false]
+        );
+    }
+
+    /// Compares the query modes on a million ranges and a million ingredients. Run with
+    /// `cargo test --release -- --ignored --nocapture` to see the timings.
+    #[test]
+    #[ignore]
+    fn benchmark_query_modes() {
+        let mut state = 0x2545f4914f6cdd1d_u64;
+        let mut random_id = || next_random(&mut state) >> 24;
+
+        let fresh_ranges = (0..1_000_000)
+            .map(|_| {
+                let start = random_id();
+                (start, start + random_id() % 1_000_000)
+            })
+            .collect_vec();
+        let available_ingredients = (0..1_000_000).map(|_| random_id()).collect_vec();
+        let data = CafeteriaData {
+            fresh_ranges: unweighted_ranges(&fresh_ranges),
+            available_ingredients,
+        };
+
+        let start = std::time::Instant::now();
+        let binary_search = find_available_fresh_ingredients(&data, QueryMode::BinarySearch);
+        println!("Binary search: {:?}", start.elapsed());
+
+        let start = std::time::Instant::now();
+        let merge_walk = find_available_fresh_ingredients(&data, QueryMode::MergeWalk);
+        println!("Merge walk: {:?}", start.elapsed());
+
+        let start = std::time::Instant::now();
+        let index_lookup = find_available_fresh_ingredients(&data, QueryMode::IndexLookup);
+        println!("Index lookups: {:?}", start.elapsed());
+
+        assert_eq!(binary_search, merge_walk);
+        assert_eq!(binary_search, index_lookup);
     }
 
     #[test]
     fn test_find_all_fresh_ingredients() {
-        let parsed = parse_input(EXAMPLE_INPUT);
+        let parsed = parse_input(EXAMPLE_INPUT).unwrap();
         assert_eq!(count_all_fresh_ingredients(parsed), 14)
     }
 
     #[test]
     fn test_combine_overlapping_ranges_separate() {
         assert_eq!(
-            combine_overlapping_ranges(vec![(1, 2)]),
+            combine_overlapping_ranges(vec![(1, 2)], false),
             vec![(1, 2)]
         );
     }
@@ -172,7 +942,7 @@ mod tests {
     #[test]
     fn test_combine_overlapping_ranges_overlap_start() {
         assert_eq!(
-            combine_overlapping_ranges(vec![(1, 2), (4, 5), (6, 10)]),
+            combine_overlapping_ranges(vec![(1, 2), (4, 5), (6, 10)], false),
             vec![(1, 2), (4, 5), (6, 10)]
         );
     }
@@ -180,7 +950,7 @@ mod tests {
     #[test]
     fn test_combine_overlapping_ranges_overlap_end() {
         assert_eq!(
-            combine_overlapping_ranges(vec![(1, 2), (2, 5), (10, 15), (12, 20)]),
+            combine_overlapping_ranges(vec![(1, 2), (2, 5), (10, 15), (12, 20)], false),
             vec![(1, 5), (10, 20)]
         );
     }
@@ -188,7 +958,7 @@ mod tests {
     #[test]
     fn test_combine_overlapping_ranges_overlap_full_first_larger() {
         assert_eq!(
-            combine_overlapping_ranges(vec![(1, 10), (3, 5)]),
+            combine_overlapping_ranges(vec![(1, 10), (3, 5)], false),
             vec![(1, 10)]
         );
     }
@@ -196,7 +966,7 @@ mod tests {
     #[test]
     fn test_combine_overlapping_ranges_overlap_full_second_larger() {
         assert_eq!(
-            combine_overlapping_ranges(vec![(3, 5), (1, 10)]),
+            combine_overlapping_ranges(vec![(3, 5), (1, 10)], false),
             vec![(1, 10)]
         );
     }
@@ -204,12 +974,48 @@ mod tests {
     #[test]
     fn test_combine_overlapping_ranges_multi() {
         assert_eq!(
-            combine_overlapping_ranges(vec![(3, 5), (10, 14), (16, 20), (12, 18)]),
+            combine_overlapping_ranges(vec![(3, 5), (10, 14), (16, 20), (12, 18)], false),
             vec![(3, 5), (10, 20)]
         );
     }
 
     #[test]
+    fn test_combine_overlapping_ranges_unsorted() {
+        assert_eq!(
+            combine_overlapping_ranges(vec![(20, 25), (1, 3), (8, 9), (2, 6), (5, 7)], false),
+            vec![(1, 7), (8, 9), (20, 25)]
+        );
+    }
+
+    #[test]
+    fn test_combine_overlapping_ranges_adjacent() {
+        assert_eq!(
+            combine_overlapping_ranges(vec![(6, 9), (3, 5), (11, 12)], false),
+            vec![(3, 5), (6, 9), (11, 12)]
+        );
+        assert_eq!(
+            combine_overlapping_ranges(vec![(6, 9), (3, 5), (11, 12)], true),
+            vec![(3, 9), (11, 12)]
+        );
+        assert_eq!(
+            combine_overlapping_ranges(vec![(10, u64::MAX), (0, 9), (u64::MAX, u64::MAX)], true),
+            vec![(0, u64::MAX)]
+        );
+    }
+
+    #[test]
+    fn test_count_ids_at_u64_boundaries() {
+        assert_eq!(count_ids(&[(0, u64::MAX)]), u64::MAX as u128 + 1);
+        assert_eq!(count_ids(&[(u64::MAX, u64::MAX)]), 1);
+        assert_eq!(count_ids(&[(0, u64::MAX - 1), (u64::MAX, u64::MAX)]), 1 << 64);
+        assert_eq!(count_ids(&[]), 0);
+
+        let data = parse_input("0-18446744073709551615\n5-10\n18446744073709551615-18446744073709551615").unwrap();
+        assert_eq!(count_all_fresh_ingredients(data), 1 << 64);
+    }
+
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_ranges_overlap() {
         assert_eq!(ranges_overlap(&(1, 2), &(3, 4)), false);
 
@@ -222,4 +1028,13 @@ mod tests {
         assert_eq!(ranges_overlap(&(1, 10), &(2, 5)), true);
         assert_eq!(ranges_overlap(&(2, 5), &(1, 10)), true);
     }
+
+    #[test]
+    fn test_ranges_adjacent() {
+        assert!(ranges_adjacent(&(1, 2), &(3, 4)));
+        assert!(!ranges_adjacent(&(3, 4), &(1, 2)));
+        assert!(!ranges_adjacent(&(1, 2), &(2, 4)));
+        assert!(!ranges_adjacent(&(1, 2), &(4, 5)));
+        assert!(!ranges_adjacent(&(1, u64::MAX), &(0, 0)));
+    }
 }
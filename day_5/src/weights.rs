@@ -0,0 +1,123 @@
+use crate::{CafeteriaData, find_containing_ranges};
+use itertools::Itertools;
+
+/// How strongly an available ingredient is vouched for: the total weight of the fresh ranges containing it and who
+/// they come from (their label, or their input line for unlabelled ranges).
+#[derive(Debug, PartialEq)]
+pub struct IngredientWeight {
+    pub ingredient: u64,
+    /// A `u128`, so that adding up any number of `u64` weights can't overflow in practice.
+    pub total_weight: u128,
+    pub suppliers: Vec<String>,
+}
+
+pub fn weigh_ingredients(data: &CafeteriaData) -> Vec<IngredientWeight> {
+    data.available_ingredients
+        .iter()
+        .zip(find_containing_ranges(data))
+        .map(|(ingredient, range_indices)| {
+            let sources = range_indices.iter().map(|index| &data.fresh_ranges[*index]).collect_vec();
+
+            IngredientWeight {
+                ingredient: *ingredient,
+                total_weight: sources.iter().map(|source| source.weight as u128).sum(),
+                suppliers: sources
+                    .iter()
+                    .map(|source| source.label.clone().unwrap_or_else(|| format!("line {}", source.line)))
+                    .collect_vec(),
+            }
+        })
+        .collect_vec()
+}
+
+pub fn render_ingredient_weights(weights: &[IngredientWeight]) -> String {
+    let width = weights.iter().map(|weight| weight.ingredient.to_string().len()).max().unwrap_or(0);
+
+    weights
+        .iter()
+        .map(|weight| {
+            if weight.suppliers.is_empty() {
+                format!("{:>width$}: weight 0", weight.ingredient)
+            } else {
+                let suppliers = weight.suppliers.join(", ");
+                format!("{:>width$}: weight {} ({suppliers})", weight.ingredient, weight.total_weight)
+            }
+        })
+        .join("\n")
+}
+
+/// Counts the IDs contained in at least `min_ranges` of the (unmerged) ranges. Sweeps over the range boundaries in
+/// order, where a range adds one to the running count at its start and removes it again right after its end, and adds
+/// up the lengths of the stretches where the count is high enough.
+pub fn count_ids_covered_at_least(fresh_ranges: &[(u64, u64)], min_ranges: usize) -> u128 {
+    // Positions are widened so that the boundary after a range ending at `u64::MAX` still fits.
+    let events = fresh_ranges
+        .iter()
+        .flat_map(|(start, end)| [(*start as u128, 1), (*end as u128 + 1, -1)])
+        .sorted_unstable()
+        .collect_vec();
+
+    let mut covered_ids = 0;
+    let mut overlapping_ranges: isize = 0;
+
+    for ((position, change), (next_position, _)) in events.iter().tuple_windows() {
+        overlapping_ranges += change;
+
+        if overlapping_ranges >= min_ranges as isize {
+            covered_ids += next_position - position;
+        }
+    }
+
+    covered_ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_input;
+
+    #[test]
+    fn test_weigh_ingredients() {
+        let data = parse_input("1-10 *2 acme\n5-6\n6-8 *3\n\n1\n6\n11").unwrap();
+
+        assert_eq!(
+            weigh_ingredients(&data),
+            vec![
+                IngredientWeight {
+                    ingredient: 1,
+                    total_weight: 2,
+                    suppliers: vec!["acme".to_string()],
+                },
+                IngredientWeight {
+                    ingredient: 6,
+                    total_weight: 6,
+                    suppliers: vec!["acme".to_string(), "line 2".to_string(), "line 3".to_string()],
+                },
+                IngredientWeight {
+                    ingredient: 11,
+                    total_weight: 0,
+                    suppliers: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_weigh_ingredients_beyond_u64() {
+        let data = parse_input("1-5 *18446744073709551615\n3-9 *18446744073709551615\n\n4").unwrap();
+
+        assert_eq!(weigh_ingredients(&data)[0].total_weight, 2 * u64::MAX as u128);
+    }
+
+    #[test]
+    fn test_count_ids_covered_at_least() {
+        let ranges = [(3, 5), (10, 14), (16, 20), (12, 18)];
+
+        assert_eq!(count_ids_covered_at_least(&ranges, 1), 14);
+        assert_eq!(count_ids_covered_at_least(&ranges, 2), 6);
+        assert_eq!(count_ids_covered_at_least(&ranges, 3), 0);
+        assert_eq!(count_ids_covered_at_least(&[(1, 5), (5, 5), (5, 9), (u64::MAX, u64::MAX)], 3), 1);
+        assert_eq!(count_ids_covered_at_least(&[(0, 2), (u64::MAX - 1, u64::MAX), (u64::MAX, u64::MAX)], 1), 5);
+        assert_eq!(count_ids_covered_at_least(&[(0, u64::MAX), (0, u64::MAX)], 2), 1 << 64);
+    }
+}
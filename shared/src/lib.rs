@@ -1,5 +1,15 @@
 use std::fs;
-use std::io::stdin;
+use std::io::{Read, stdin};
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq)]
+pub enum ArgumentError {
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidValue { option: String, value: String },
+    UnexpectedArgument(String),
+    ConflictingOptions(String, String),
+}
 
 pub fn print_program_header(day: u8, problem_name: &str) {
     println!("+-----------------------------------------------+");
@@ -16,6 +26,44 @@ pub fn read_data(day: u8) -> String {
     String::from_utf8(data).expect("Input file cannot be read as UTF-8")
 }
 
+pub fn read_data_file(path: &str) -> String {
+    let data = fs::read(path).unwrap_or_else(|_| panic!("Cannot open the input file {path}."));
+
+    String::from_utf8(data).expect("Input file cannot be read as UTF-8")
+}
+
+/// Reads the puzzle input from the given file, or from stdin when the path is `-`. Without a path, the day's default
+/// data file is used.
+pub fn read_input(day: u8, path: Option<&str>) -> String {
+    match path {
+        Some("-") => read_stdin_to_end(),
+        Some(path) => read_data_file(path),
+        None => read_data(day),
+    }
+}
+
+fn read_stdin_to_end() -> String {
+    let mut data = String::new();
+    stdin().read_to_string(&mut data).expect("Cannot read the input from stdin");
+
+    data
+}
+
+/// Takes the value following a command line option and parses it.
+pub fn parse_option_value<T: FromStr>(
+    option: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<T, ArgumentError> {
+    let value = args
+        .next()
+        .ok_or_else(|| ArgumentError::MissingValue(option.to_string()))?;
+
+    value.parse().map_err(|_| ArgumentError::InvalidValue {
+        option: option.to_string(),
+        value,
+    })
+}
+
 pub fn read_data_stdin() -> String {
     println!("Write \"####END####\" to end the input.");
 